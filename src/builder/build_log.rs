@@ -0,0 +1,45 @@
+use std::fs::{File, OpenOptions};
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Appends timestamped lines to a build log file.
+/// Each line is flushed as it is written so the record survives a build
+/// which fails partway through.
+pub(crate) struct BuildLog {
+    file: Option<LineWriter<File>>,
+}
+
+impl BuildLog {
+    pub(crate) fn open<P: AsRef<Path>>(path: Option<P>) -> std::io::Result<Self> {
+        let file = match path {
+            Some(path) => {
+                let file = OpenOptions::new().create(true).append(true).open(path)?;
+                Some(LineWriter::new(file))
+            }
+            None => None,
+        };
+        Ok(Self { file })
+    }
+
+    pub(crate) fn disabled() -> Self {
+        Self { file: None }
+    }
+
+    pub(crate) fn record(&mut self, message: &str) -> std::io::Result<()> {
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        writeln!(
+            file,
+            "[{}.{:03}] {}",
+            timestamp.as_secs(),
+            timestamp.subsec_millis(),
+            message
+        )
+    }
+}
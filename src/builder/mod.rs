@@ -1,13 +1,20 @@
+mod build_log;
 mod node;
+mod requirement;
 
 use std::cell::RefCell;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use topologic::AcyclicDependencyGraph;
 
+use build_log::BuildLog;
 pub use node::Node;
+pub use requirement::Requirement;
 
 #[derive(Debug)]
 pub enum Registration {
@@ -15,6 +22,83 @@ pub enum Registration {
     Concrete(PathBuf),
 }
 
+/// Error returned by `Builder::generate` when the build is cancelled.
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "build cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Error returned when resources depend on each other in a loop.
+/// Lists the ids of the resources in the loop, starting and ending with the
+/// same resource, along with their `Debug` forms where available.
+#[derive(Debug)]
+pub struct DependencyCycle {
+    pub ids: Vec<u64>,
+    pub resources: HashMap<u64, String>,
+}
+
+impl std::fmt::Display for DependencyCycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let resources: Vec<String> = self
+            .ids
+            .iter()
+            .map(|id| match self.resources.get(id) {
+                Some(resource) => format!("{} ({})", id, resource),
+                None => id.to_string(),
+            })
+            .collect();
+        write!(f, "dependency cycle: {}", resources.join(" -> "))
+    }
+}
+
+impl std::error::Error for DependencyCycle {}
+
+/// Error returned when a resource registers an output path which another,
+/// different resource already produces. Holds the `Debug` forms of both.
+#[derive(Debug)]
+pub struct OutputCollision {
+    pub path: PathBuf,
+    pub existing: String,
+    pub resource: String,
+}
+
+impl std::fmt::Display for OutputCollision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "output {:?} is produced by both {} and {}",
+            self.path, self.existing, self.resource
+        )
+    }
+}
+
+impl std::error::Error for OutputCollision {}
+
+/// The resources which failed in `Builder::generate_all`, with their errors.
+pub type Failures = Vec<(Node, Box<dyn std::error::Error>)>;
+
+/// Normalizes a path so that equivalent spellings compare equal, e.g.
+/// `./site/index.html` and `site//index.html` both become
+/// `site/index.html`. `..` components are kept, as resolving them requires
+/// the filesystem.
+pub fn normalize<P: AsRef<Path>>(path: P) -> PathBuf {
+    let normalized: PathBuf = path
+        .as_ref()
+        .components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .collect();
+    if normalized.as_os_str().is_empty() {
+        return PathBuf::from(".");
+    }
+    normalized
+}
+
 /// A resource that can be built.
 pub trait Build: std::fmt::Debug {
     /// Returns a reference to the resource as `dyn Any`.
@@ -50,6 +134,32 @@ pub trait Build: std::fmt::Debug {
     fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         Ok(())
     }
+
+    /// Returns the paths the resource writes besides its registered output,
+    /// e.g. names derived from the content, so that `Builder::clean` keeps
+    /// them.
+    fn unregistered_outputs(&self) -> Vec<PathBuf> {
+        vec![]
+    }
+
+    /// Returns the external tools which must be installed for `generate` to
+    /// succeed. Used to check a build up front with
+    /// `Builder::check_requirements`.
+    fn external_requirements(&self) -> Vec<Requirement> {
+        vec![]
+    }
+}
+
+type OnComplete = Box<dyn Fn(&Node, Duration)>;
+type OnProgress = Box<dyn Fn(usize, usize)>;
+
+// the outputs under `root` which a resource listed while declaring its
+// dependencies, so that matching outputs registered later can be rejected
+struct Listing {
+    resource: u64,
+    root: PathBuf,
+    filter: fn(&Path) -> bool,
+    listed: Vec<PathBuf>,
 }
 
 pub struct Builder {
@@ -58,6 +168,15 @@ pub struct Builder {
     next_id: u64,
     roots: Vec<Node>,
     output: HashMap<PathBuf, Node>,
+    log_file: Option<PathBuf>,
+    labels: HashMap<(u64, u64), String>,
+    resolving: Vec<(u64, Rc<RefCell<dyn Build>>)>,
+    cancel: Option<Arc<AtomicBool>>,
+    on_complete: Option<OnComplete>,
+    on_progress: Option<OnProgress>,
+    unresolved: Vec<(u64, PathBuf)>,
+    listings: Vec<Listing>,
+    registered: Vec<PathBuf>,
 }
 
 impl Builder {
@@ -68,6 +187,15 @@ impl Builder {
             next_id: 0,
             roots: vec![],
             output: HashMap::new(),
+            log_file: None,
+            labels: HashMap::new(),
+            resolving: vec![],
+            cancel: None,
+            on_complete: None,
+            on_progress: None,
+            unresolved: vec![],
+            listings: vec![],
+            registered: vec![],
         }
     }
 
@@ -80,7 +208,9 @@ impl Builder {
         resource: T,
     ) -> Result<Node, Box<dyn std::error::Error>> {
         let node = self.next(Rc::new(RefCell::new(resource)))?;
-        self.require_node(node)
+        let node = self.require_node(node)?;
+        self.link()?;
+        Ok(node)
     }
 
     pub fn require_ref<T: Build + 'static>(
@@ -88,41 +218,429 @@ impl Builder {
         resource: Rc<RefCell<T>>,
     ) -> Result<Node, Box<dyn std::error::Error>> {
         let node = self.next(resource)?;
-        self.require_node(node)
+        let node = self.require_node(node)?;
+        self.link()?;
+        Ok(node)
+    }
+
+    /// Requires a shared resource whose type is only known as `dyn Build`.
+    pub fn require_dyn(
+        &mut self,
+        resource: Rc<RefCell<dyn Build>>,
+    ) -> Result<Node, Box<dyn std::error::Error>> {
+        let node = self.next(resource)?;
+        let node = self.require_node(node)?;
+        self.link()?;
+        Ok(node)
     }
 
     pub fn require_node(&mut self, node: Node) -> Result<Node, Box<dyn std::error::Error>> {
         self.nodes.insert(node.id, node.clone());
         self.roots.push(node.clone());
         for dependency in node.clone().dependencies {
-            self.dependency_graph
-                .depend_on(node.clone(), dependency.clone())
-                .unwrap();
+            self.depend_on(&node, &dependency)?;
             self.require_node(dependency.clone())?;
         }
         Ok(node)
     }
 
+    /// Appends a timestamped record of each generated resource, any error and
+    /// the total build time to the file at `path` during `generate`.
+    pub fn log_file<P: AsRef<Path>>(&mut self, path: P) {
+        self.log_file = Some(path.as_ref().to_path_buf());
+    }
+
+    /// Sets a token which cancels `generate` when set to true.
+    /// The token is checked before each resource is generated, so a
+    /// cancelled build stops between resources and never mid-write,
+    /// returning a `Cancelled` error.
+    pub fn cancel_token(&mut self, token: Arc<AtomicBool>) {
+        self.cancel = Some(token);
+    }
+
+    /// Sets a callback invoked after each resource is generated with its
+    /// node and how long `Build::generate` took, e.g. to find slow
+    /// resources. It is invoked for failed resources too.
+    pub fn on_complete<F: Fn(&Node, Duration) + 'static>(&mut self, callback: F) {
+        self.on_complete = Some(Box::new(callback));
+    }
+
+    /// Sets a callback invoked as `(completed, total)` after each resource
+    /// is visited during generation, e.g. to drive a progress bar. `total`
+    /// is the number of resources in the dependency graph, and resources
+    /// skipped by `generate_all` count as completed.
+    pub fn on_progress<F: Fn(usize, usize) + 'static>(&mut self, callback: F) {
+        self.on_progress = Some(Box::new(callback));
+    }
+
+    /// Returns the resources in the order `generate` visits them, as layers
+    /// in which each resource depends only on resources in earlier layers.
+    /// Each layer is sorted by node id so the order is the same on every
+    /// run. Nothing is generated.
+    pub fn build_order(&self) -> Vec<Vec<Node>> {
+        let mut layers: Vec<Vec<Node>> = self
+            .dependency_graph
+            .get_forward_dependency_topological_layers()
+            .into_iter()
+            .map(|layer| layer.into_iter().collect())
+            .collect();
+
+        // resources without dependencies or dependents have no edges, so
+        // they are missing from the graph but can be generated first
+        let graphed: HashSet<u64> = layers.iter().flatten().map(|node| node.id).collect();
+        let isolated: Vec<Node> = self
+            .nodes
+            .values()
+            .filter(|node| !graphed.contains(&node.id))
+            .cloned()
+            .collect();
+        if !isolated.is_empty() {
+            match layers.first_mut() {
+                Some(first) => first.extend(isolated),
+                None => layers.push(isolated),
+            }
+        }
+
+        for layer in layers.iter_mut() {
+            layer.sort_by_key(|node| node.id);
+        }
+        layers
+    }
+
     pub fn generate(self) -> Result<(), Box<dyn std::error::Error>> {
+        let start = Instant::now();
+        let mut log = BuildLog::open(self.log_file.as_ref())?;
+
         // perform a topological sort on the dependency graph
-        let layers = self
-            .dependency_graph
-            .get_forward_dependency_topological_layers();
+        let layers = self.build_order();
+        let total = layers.iter().map(|layer| layer.len()).sum();
 
         // generate the site
+        let mut generated = 0;
+        for layer in &layers {
+            for node in layer {
+                if let Some(cancel) = &self.cancel {
+                    if cancel.load(Ordering::SeqCst) {
+                        log.record(&format!("cancelled after {} resources", generated))?;
+                        return Err(Box::new(Cancelled));
+                    }
+                }
+                log::debug!("generating {:?}", node);
+                log.record(&format!("generating {:?}", node))?;
+                if let Err(error) = self.generate_node(node) {
+                    log.record(&format!("failed {}: {}", node.id, error))?;
+                    return Err(error);
+                }
+                generated += 1;
+                self.progress(generated, total);
+            }
+        }
+
+        let summary = format!("generated {} resources in {:?}", generated, start.elapsed());
+        log::info!("{}", summary);
+        log.record(&summary)?;
+        Ok(())
+    }
+
+    /// Generates every resource it can, rather than stopping at the first
+    /// error like `generate`. Resources which depend on a failed resource
+    /// are skipped, and all failures are returned together with the node
+    /// of the resource, in the order they occurred. The build log is best
+    /// effort here, so failing to write it does not stop the build.
+    pub fn generate_all(self) -> Result<(), Failures> {
+        let start = Instant::now();
+        let mut log = BuildLog::open(self.log_file.as_ref()).unwrap_or_else(|e| {
+            log::warn!("failed to open build log: {}", e);
+            BuildLog::disabled()
+        });
+        let layers = self.build_order();
+
+        let total = layers.iter().map(|layer| layer.len()).sum();
+
+        let mut errors: Failures = vec![];
+        let mut failed: HashSet<u64> = HashSet::new();
+        let mut generated = 0;
+        let mut completed = 0;
         for layer in &layers {
             for node in layer {
-                node.resource().borrow_mut().generate()?;
+                completed += 1;
+                let dependencies = &self.nodes.get(&node.id).unwrap_or(node).dependencies;
+                if let Some(dependency) = dependencies.iter().find(|d| failed.contains(&d.id)) {
+                    let message = format!("skipped as dependency {} failed", dependency.id);
+                    log.record(&format!("skipped {}: {}", node.id, message))
+                        .ok();
+                    failed.insert(node.id);
+                    errors.push((node.clone(), message.into()));
+                    self.progress(completed, total);
+                    continue;
+                }
+                if let Some(cancel) = &self.cancel {
+                    if cancel.load(Ordering::SeqCst) {
+                        log.record(&format!("cancelled after {} resources", generated))
+                            .ok();
+                        errors.push((node.clone(), Box::new(Cancelled)));
+                        return Err(errors);
+                    }
+                }
+                log::debug!("generating {:?}", node);
+                log.record(&format!("generating {:?}", node)).ok();
+                if let Err(error) = self.generate_node(node) {
+                    log.record(&format!("failed {}: {}", node.id, error)).ok();
+                    failed.insert(node.id);
+                    errors.push((node.clone(), error));
+                    self.progress(completed, total);
+                    continue;
+                }
+                generated += 1;
+                self.progress(completed, total);
+            }
+        }
+
+        let summary = format!(
+            "generated {} resources with {} failures in {:?}",
+            generated,
+            errors.len(),
+            start.elapsed()
+        );
+        log::info!("{}", summary);
+        log.record(&summary).ok();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Returns the external tools required by all registered resources.
+    pub fn requirements(&self) -> Vec<Requirement> {
+        let mut ids: Vec<&u64> = self.nodes.keys().collect();
+        ids.sort();
+        let mut requirements: Vec<Requirement> = vec![];
+        for id in ids {
+            for requirement in self.nodes[id].resource.borrow().external_requirements() {
+                if !requirements.contains(&requirement) {
+                    requirements.push(requirement);
+                }
             }
         }
+        requirements
+    }
 
+    /// Checks that every external tool required by the registered resources
+    /// can be found on `PATH`, returning an error naming any that are missing.
+    pub fn check_requirements(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let missing: Vec<String> = self
+            .requirements()
+            .into_iter()
+            .filter(|requirement| requirement.locate().is_none())
+            .map(|requirement| requirement.to_string())
+            .collect();
+        if !missing.is_empty() {
+            let message = format!("missing required tools: {}", missing.join(", "));
+            return Err(message.into());
+        }
         Ok(())
     }
 
+    /// Labels the edge from the resource currently declaring its dependencies
+    /// to `dependency` with the kind of relationship, e.g. "directory".
+    /// Intended to be called from `Build::dependencies`.
+    pub fn label(&mut self, dependency: &Node, label: &str) {
+        if let Some((from, _)) = self.resolving.last() {
+            self.labels
+                .insert((*from, dependency.id), label.to_string());
+        }
+    }
+
+    /// Returns the labels of dependency edges keyed by `(from, to)` node ids.
+    pub fn labels(&self) -> HashMap<(u64, u64), String> {
+        self.labels.clone()
+    }
+
+    /// Renders the dependency graph in Graphviz DOT format, e.g. for
+    /// `dot -Tsvg`. Edges point from a resource to its dependencies.
+    pub fn to_dot(&self) -> String {
+        let escape = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut ids: Vec<&u64> = self.nodes.keys().collect();
+        ids.sort();
+
+        let mut dot = String::from("digraph blanket {\n");
+        for id in &ids {
+            let resource = format!("{:?}", self.nodes[id].resource.borrow());
+            dot.push_str(&format!(
+                "    {} [label=\"{}: {}\"];\n",
+                id,
+                id,
+                escape(&resource)
+            ));
+        }
+        for id in &ids {
+            for dependency in &self.nodes[id].dependencies {
+                match self.labels.get(&(**id, dependency.id)) {
+                    Some(label) => dot.push_str(&format!(
+                        "    {} -> {} [label=\"{}\"];\n",
+                        id,
+                        dependency.id,
+                        escape(label)
+                    )),
+                    None => dot.push_str(&format!("    {} -> {};\n", id, dependency.id)),
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Removes files and directories under `root` which are not registered
+    /// outputs, e.g. left behind by renamed or deleted resources. Paths
+    /// returned by `Build::unregistered_outputs` are kept too.
+    /// Directories are removed once they contain nothing registered.
+    /// Returns the removed paths.
+    pub fn clean<P: AsRef<Path>>(
+        &self,
+        root: P,
+    ) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+        self.stale(root.as_ref(), false)
+    }
+
+    /// Returns the paths `clean` would remove without removing them.
+    pub fn clean_dry_run<P: AsRef<Path>>(
+        &self,
+        root: P,
+    ) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+        self.stale(root.as_ref(), true)
+    }
+
+    fn stale(
+        &self,
+        root: &Path,
+        dry_run: bool,
+    ) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+        // compare resolved paths so that differently spelled paths match
+        // the final component is not resolved so links are not followed
+        fn resolve(path: &Path) -> Option<PathBuf> {
+            match (path.parent(), path.file_name()) {
+                (Some(parent), Some(name)) => {
+                    let parent = if parent.as_os_str().is_empty() {
+                        Path::new(".")
+                    } else {
+                        parent
+                    };
+                    Some(parent.canonicalize().ok()?.join(name))
+                }
+                _ => path.canonicalize().ok(),
+            }
+        }
+
+        let unregistered: Vec<PathBuf> = self
+            .nodes
+            .values()
+            .flat_map(|node| node.resource.borrow().unregistered_outputs())
+            .collect();
+        let registered: HashSet<PathBuf> = self
+            .output
+            .keys()
+            .chain(&unregistered)
+            .filter_map(|p| resolve(p))
+            .collect();
+        let mut removed: Vec<PathBuf> = vec![];
+        let mut removed_resolved: HashSet<PathBuf> = HashSet::new();
+
+        // visit directory contents before the directories themselves
+        for entry in walkdir::WalkDir::new(root)
+            .min_depth(1)
+            .contents_first(true)
+        {
+            let entry = entry?;
+            let path = entry.path();
+            let resolved = resolve(path).ok_or(format!("failed to resolve {:?}", path))?;
+            if registered.contains(&resolved) {
+                continue;
+            }
+            if entry.file_type().is_dir() {
+                let mut empty = true;
+                for child in std::fs::read_dir(path)? {
+                    let child = resolve(&child?.path());
+                    if !child.is_some_and(|child| removed_resolved.contains(&child)) {
+                        empty = false;
+                        break;
+                    }
+                }
+                if !empty {
+                    continue;
+                }
+                if !dry_run {
+                    std::fs::remove_dir(path)?;
+                }
+            } else if !dry_run {
+                std::fs::remove_file(path)?;
+            }
+            removed.push(path.to_path_buf());
+            removed_resolved.insert(resolved);
+        }
+        Ok(removed)
+    }
+
     pub fn output(&self) -> HashMap<PathBuf, Node> {
         self.output.clone()
     }
 
+    /// Returns the node of the resource registered to produce `path`, if any.
+    /// Resources which read files produced by other resources can depend on
+    /// this node so that the file is generated before it is read.
+    ///
+    /// When called from `Build::dependencies` before anything produces
+    /// `path`, the lookup is remembered and the edge is added once a
+    /// resource producing `path` is required, so producers may be required
+    /// before or after the resources reading their output.
+    pub fn producer<P: AsRef<Path>>(&mut self, path: P) -> Option<Node> {
+        let path = normalize(path);
+        match self.output.get(&path) {
+            Some(node) => Some(self.nodes.get(&node.id).unwrap_or(node).clone()),
+            None => {
+                if let Some((from, _)) = self.resolving.last() {
+                    self.unresolved.push((*from, path));
+                }
+                None
+            }
+        }
+    }
+
+    /// Returns the registered outputs under `root` for which `filter` returns
+    /// true, sorted, e.g. the pages to list in a sitemap.
+    ///
+    /// When called from `Build::dependencies`, requiring a matching output
+    /// afterwards fails, as it would be missing from the list. Resources
+    /// which list outputs must therefore be required after them.
+    pub fn outputs_under<P: AsRef<Path>>(
+        &mut self,
+        root: P,
+        filter: fn(&Path) -> bool,
+    ) -> Vec<PathBuf> {
+        // outputs are keyed without a leading `.`, so the current directory
+        // is the empty prefix
+        let root = match normalize(root) {
+            root if root == Path::new(".") => PathBuf::new(),
+            root => root,
+        };
+        let mut listed: Vec<PathBuf> = self
+            .output
+            .keys()
+            .filter(|path| path.starts_with(&root) && filter(path))
+            .cloned()
+            .collect();
+        listed.sort();
+        if let Some((resource, _)) = self.resolving.last() {
+            self.listings.push(Listing {
+                resource: *resource,
+                root,
+                filter,
+                listed: listed.clone(),
+            });
+        }
+        listed
+    }
+
     pub fn nodes(&self) -> HashMap<u64, Node> {
         self.nodes.clone()
     }
@@ -135,11 +653,154 @@ impl Builder {
         self.dependency_graph.clone()
     }
 
+    fn progress(&self, completed: usize, total: usize) {
+        if let Some(on_progress) = &self.on_progress {
+            on_progress(completed, total);
+        }
+    }
+
+    // adds the edge from `node` to `dependency`, failing if it closes a loop
+    fn depend_on(
+        &mut self,
+        node: &Node,
+        dependency: &Node,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self
+            .dependency_graph
+            .depend_on(node.clone(), dependency.clone())
+            .is_ok()
+        {
+            return Ok(());
+        }
+
+        // the graph already leads from the dependency back to node
+        let mut ids = vec![node.id];
+        ids.extend(self.path(dependency, node.id));
+        let resources = ids
+            .iter()
+            .filter_map(|id| self.nodes.get(id))
+            .chain([node, dependency])
+            .map(|node| (node.id, format!("{:?}", node.resource.borrow())))
+            .collect();
+        Err(Box::new(DependencyCycle { ids, resources }))
+    }
+
+    // once no resource is resolving, adds the edges to producers which were
+    // required after the resources reading their output and rejects outputs
+    // required after a resource listed the outputs around them
+    fn link(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.resolving.is_empty() {
+            return Ok(());
+        }
+
+        let (ready, unresolved): (Vec<_>, Vec<_>) = std::mem::take(&mut self.unresolved)
+            .into_iter()
+            .partition(|(_, path)| self.output.contains_key(path));
+        self.unresolved = unresolved;
+        for (id, path) in ready {
+            let producer = self.output[&path].id;
+            let (Some(node), Some(producer)) = (
+                self.nodes.get(&id).cloned(),
+                self.nodes.get(&producer).cloned(),
+            ) else {
+                continue;
+            };
+            if node.id == producer.id {
+                continue;
+            }
+            self.depend_on(&node, &producer)?;
+            if let Some(node) = self.nodes.get_mut(&id) {
+                node.dependencies.push(producer);
+            }
+        }
+
+        for path in std::mem::take(&mut self.registered) {
+            for listing in &self.listings {
+                if path.starts_with(&listing.root)
+                    && (listing.filter)(&path)
+                    && !listing.listed.contains(&path)
+                {
+                    let lister = self
+                        .output
+                        .iter()
+                        .find(|(_, node)| node.id == listing.resource)
+                        .map(|(path, _)| format!("{:?}", path))
+                        .unwrap_or_else(|| listing.resource.to_string());
+                    let message = format!(
+                        "{:?} was required after {}, which lists the outputs under {:?} and must be required after them",
+                        path, lister, listing.root
+                    );
+                    return Err(message.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // generates a single resource, timing it for the completion callback
+    fn generate_node(&self, node: &Node) -> Result<(), Box<dyn std::error::Error>> {
+        let start = Instant::now();
+        let result = node.resource().borrow_mut().generate();
+        if let Some(on_complete) = &self.on_complete {
+            on_complete(node, start.elapsed());
+        }
+        result
+    }
+
+    // finds the ids along the required dependencies from `from` to `to`,
+    // falling back to the direct edge when no such path is known
+    fn path(&self, from: &Node, to: u64) -> Vec<u64> {
+        let mut visited = HashSet::new();
+        let mut stack = vec![vec![from.id]];
+        while let Some(path) = stack.pop() {
+            let id = *path.last().unwrap();
+            if id == to {
+                return path;
+            }
+            if !visited.insert(id) {
+                continue;
+            }
+            let dependencies = match self.nodes.get(&id) {
+                Some(node) => &node.dependencies,
+                None if id == from.id => &from.dependencies,
+                None => continue,
+            };
+            for dependency in dependencies.iter().rev() {
+                let mut next = path.clone();
+                next.push(dependency.id);
+                stack.push(next);
+            }
+        }
+        vec![from.id, to]
+    }
+
+    // builds the cycle error for `resource`, which is currently resolving
+    fn cycle(&self, resource: &Rc<RefCell<dyn Build>>) -> DependencyCycle {
+        let start = self
+            .resolving
+            .iter()
+            .position(|(_, resolving)| Rc::ptr_eq(resolving, resource))
+            .unwrap_or(0);
+        let mut ids: Vec<u64> = self.resolving[start..].iter().map(|(id, _)| *id).collect();
+        if let Some(first) = ids.first() {
+            ids.push(*first);
+        }
+        DependencyCycle {
+            ids,
+            resources: HashMap::new(),
+        }
+    }
+
     fn next(
         &mut self,
         resource: Rc<RefCell<dyn Build>>,
     ) -> Result<Node, Box<dyn std::error::Error>> {
-        let optional_id = resource.borrow().id();
+        // a resource which is already borrowed is still declaring its
+        // dependencies further up the stack, so requiring it again is a loop
+        let optional_id = match resource.try_borrow() {
+            Ok(resource) => resource.id(),
+            Err(_) => return Err(Box::new(self.cycle(&resource))),
+        };
         let node = match optional_id {
             Some(id) => {
                 let existing = self.nodes.get(&id);
@@ -155,7 +816,10 @@ impl Builder {
             None => {
                 let id = self.next_id;
                 self.next_id += 1;
-                let registration = resource.borrow_mut().register(id)?;
+                let registration = match resource.borrow_mut().register(id)? {
+                    Registration::Concrete(path) => Registration::Concrete(normalize(path)),
+                    registration => registration,
+                };
 
                 // check for existing node
                 let existing = match registration {
@@ -164,10 +828,12 @@ impl Builder {
                         Some(node) => {
                             let existing = node.resource.borrow();
                             if !existing.equals(resource.clone()) {
-                                println!("path: {:?}", path);
-                                println!("existing: {:?}", existing);
-                                println!("resource: {:?}", resource);
-                                return Err("output already exists with different data".into());
+                                let collision = OutputCollision {
+                                    path: path.clone(),
+                                    existing: format!("{:?}", existing),
+                                    resource: format!("{:?}", resource.borrow()),
+                                };
+                                return Err(Box::new(collision));
                             }
                             Some(node.clone())
                         }
@@ -179,13 +845,32 @@ impl Builder {
                 }
 
                 // create new node
-                let dependencies = resource.borrow_mut().dependencies(self)?;
+                self.resolving.push((id, resource.clone()));
+                let mut borrowed = resource.borrow_mut();
+                let dependencies = match borrowed.dependencies(self) {
+                    Ok(dependencies) => Ok(dependencies),
+                    Err(mut e) => {
+                        // describe the resource while it is still borrowed so
+                        // that references back to it along the cycle are not
+                        // followed when formatting
+                        if let Some(cycle) = e.downcast_mut::<DependencyCycle>() {
+                            if cycle.ids.contains(&id) {
+                                cycle.resources.insert(id, format!("{:?}", &*borrowed));
+                            }
+                        }
+                        Err(e)
+                    }
+                };
+                drop(borrowed);
+                self.resolving.pop();
+                let dependencies = dependencies?;
                 let node = Node::new(id, resource.clone(), dependencies);
                 match registration {
                     Registration::Virtual() => {}
                     Registration::Concrete(ref path) => {
                         self.output
                             .insert(path.clone(), Node::new(id, resource.clone(), vec![]));
+                        self.registered.push(path.clone());
                     }
                 };
                 node
@@ -207,6 +892,10 @@ mod tests {
         equals: bool,
         content: Option<String>,
         shared: Option<Rc<RefCell<Mock>>>,
+        requirements: Vec<Requirement>,
+        fails: bool,
+        reads: Option<PathBuf>,
+        lists: bool,
     }
 
     #[derive(Clone)]
@@ -215,6 +904,10 @@ mod tests {
         equals: bool,
         content: Option<String>,
         shared: Option<Rc<RefCell<Mock>>>,
+        requirements: Vec<Requirement>,
+        fails: bool,
+        reads: Option<PathBuf>,
+        lists: bool,
     }
 
     impl MockBuilder {
@@ -224,6 +917,10 @@ mod tests {
                 equals: false,
                 content: None,
                 shared: None,
+                requirements: vec![],
+                fails: false,
+                reads: None,
+                lists: false,
             }
         }
         fn path<P: AsRef<Path>>(mut self, path: P) -> Self {
@@ -242,6 +939,22 @@ mod tests {
             self.shared = Some(shared);
             self
         }
+        fn requirement(mut self, requirement: Requirement) -> Self {
+            self.requirements.push(requirement);
+            self
+        }
+        fn fails(mut self, fails: bool) -> Self {
+            self.fails = fails;
+            self
+        }
+        fn reads<P: AsRef<Path>>(mut self, path: P) -> Self {
+            self.reads = Some(path.as_ref().to_path_buf());
+            self
+        }
+        fn lists(mut self, lists: bool) -> Self {
+            self.lists = lists;
+            self
+        }
         fn build(self) -> Mock {
             Mock {
                 id: None,
@@ -249,6 +962,10 @@ mod tests {
                 equals: self.equals,
                 content: self.content,
                 shared: self.shared,
+                requirements: self.requirements,
+                fails: self.fails,
+                reads: self.reads,
+                lists: self.lists,
             }
         }
     }
@@ -289,13 +1006,26 @@ mod tests {
             let mut dependencies = vec![];
             if let Some(inner) = self.shared.as_ref() {
                 let node = builder.require_ref(inner.clone())?;
+                builder.label(&node, "shared");
+                dependencies.push(node);
+            }
+            if let Some(node) = self.reads.as_ref().and_then(|path| builder.producer(path)) {
                 dependencies.push(node);
             }
+            if self.lists {
+                builder.outputs_under("site", |path| path.extension().is_some());
+            }
             Ok(dependencies)
         }
         fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            if self.fails {
+                return Err("mock failed".into());
+            }
             Ok(())
         }
+        fn external_requirements(&self) -> Vec<Requirement> {
+            self.requirements.clone()
+        }
     }
 
     mod test_mock {
@@ -357,6 +1087,7 @@ mod tests {
             assert_eq!(dependency.id, 1);
         }
 
+        #[test]
         fn test_require_unique_resources_identical_paths_collide() {
             let mut builder = Builder::new();
             const REGISTRATION_PATH: &str = "identical";
@@ -376,6 +1107,17 @@ mod tests {
                 .build();
             let result = builder.require(unique_mock);
             assert!(matches!(result, Err(_)));
+
+            // the error names the path and both resources
+            let error = result.unwrap_err();
+            let collision = error.downcast_ref::<OutputCollision>().unwrap();
+            assert_eq!(collision.path, PathBuf::from(REGISTRATION_PATH));
+            assert!(collision.existing.contains("id: Some(0)"));
+            assert!(collision.resource.contains("id: Some(1)"));
+            let message = collision.to_string();
+            assert!(message.contains("\"identical\""));
+            assert!(message.contains(&collision.existing));
+            assert!(message.contains(&collision.resource));
         }
 
         #[test]
@@ -423,6 +1165,44 @@ mod tests {
             assert!(matches!(result, Ok(_)));
         }
 
+        #[test]
+        fn test_normalize() {
+            assert_eq!(
+                normalize("./site/index.html"),
+                PathBuf::from("site/index.html")
+            );
+            assert_eq!(
+                normalize("site//./index.html"),
+                PathBuf::from("site/index.html")
+            );
+            assert_eq!(
+                normalize("/site/../index.html"),
+                PathBuf::from("/site/../index.html")
+            );
+            assert_eq!(normalize("./"), PathBuf::from("."));
+        }
+
+        #[test]
+        fn test_require_equivalent_paths_collide() {
+            // equivalent spellings of a path are the same output
+            let mut builder = Builder::new();
+            let mock = MockBuilder::new().path("site/index.html").build();
+            builder.require(mock).unwrap();
+            let mock = MockBuilder::new().path("./site//index.html").build();
+            assert!(builder.require(mock).is_err());
+
+            // so an identical resource resolves to the existing node
+            let mut builder = Builder::new();
+            let mock = MockBuilder::new()
+                .path("site/index.html")
+                .equals(true)
+                .build();
+            let node = builder.require(mock).unwrap();
+            let mock = MockBuilder::new().path("./site/index.html").build();
+            assert_eq!(builder.require(mock).unwrap().id, node.id);
+            assert_eq!(builder.producer("site/./index.html").unwrap().id, node.id);
+        }
+
         #[test]
         fn test_common_resource() {
             let mut builder = Builder::new();
@@ -451,5 +1231,408 @@ mod tests {
             assert_eq!(builder.nodes.len(), 3);
             assert_eq!(common.borrow().id, Some(1));
         }
+
+        #[test]
+        fn test_labels() {
+            let mut builder = Builder::new();
+            let mocker = MockBuilder::new();
+
+            // the dependent labels the edge to its shared resource
+            let common = Rc::new(RefCell::new(mocker.clone().build()));
+            let dependent = mocker.clone().shared(common.clone()).build();
+            builder.require(dependent).unwrap();
+            let labels = builder.labels();
+            assert_eq!(labels.len(), 1);
+            assert_eq!(labels.get(&(0, 1)), Some(&String::from("shared")));
+
+            // labels are not applied outside of dependency resolution
+            let node = builder.require(mocker.clone().build()).unwrap();
+            builder.label(&node, "unused");
+            assert_eq!(builder.labels().len(), 1);
+        }
+
+        #[test]
+        fn test_cycle() {
+            let mut builder = Builder::new();
+            let mocker = MockBuilder::new();
+
+            // two resources which depend on each other
+            let first = Rc::new(RefCell::new(mocker.clone().build()));
+            let second = Rc::new(RefCell::new(mocker.clone().shared(first.clone()).build()));
+            first.borrow_mut().shared = Some(second.clone());
+
+            // the error names both resources in the loop
+            let error = builder.require_ref(first.clone()).unwrap_err();
+            let cycle = error.downcast_ref::<DependencyCycle>().unwrap();
+            assert_eq!(cycle.ids, vec![0, 1, 0]);
+            assert!(cycle.resources[&0].starts_with("Mock"));
+            assert!(cycle.resources[&1].starts_with("Mock"));
+            assert!(error.to_string().starts_with("dependency cycle: 0 (Mock"));
+
+            // break the reference cycle
+            first.borrow_mut().shared = None;
+        }
+
+        #[test]
+        fn test_cycle_path() {
+            let mut builder = Builder::new();
+            let mocker = MockBuilder::new();
+            let resource =
+                || -> Rc<RefCell<dyn Build>> { Rc::new(RefCell::new(mocker.clone().build())) };
+
+            // a chain of nodes 0 -> 1 -> 2
+            let last = Node::new(2, resource(), vec![]);
+            let middle = Node::new(1, resource(), vec![last.clone()]);
+            let first = Node::new(0, resource(), vec![middle]);
+            builder.require_node(first.clone()).unwrap();
+
+            // closing the loop reports every node along it
+            let closing = Node::new(2, last.resource(), vec![first]);
+            let error = builder.require_node(closing).unwrap_err();
+            let cycle = error.downcast_ref::<DependencyCycle>().unwrap();
+            assert_eq!(cycle.ids, vec![2, 0, 1, 2]);
+            assert_eq!(cycle.resources.len(), 3);
+        }
+
+        #[test]
+        fn test_to_dot() {
+            let mut builder = Builder::new();
+            let mocker = MockBuilder::new();
+            let common = Rc::new(RefCell::new(mocker.clone().build()));
+            let dependent = mocker.clone().shared(common.clone()).build();
+            builder.require(dependent).unwrap();
+
+            let dot = builder.to_dot();
+            assert!(dot.starts_with("digraph blanket {\n"));
+            assert!(dot.ends_with("}\n"));
+            assert!(dot.contains("    0 [label=\"0: "));
+            assert!(dot.contains("    1 [label=\"1: "));
+            assert!(dot.contains("    0 -> 1 [label=\"shared\"];\n"));
+            assert!(!dot.contains("1 -> 0"));
+        }
+
+        #[test]
+        fn test_requirements() {
+            let mut builder = Builder::new();
+            let present = Requirement::new("sh");
+            let missing = Requirement::new("blanket-rs-missing-tool").version(">=1.0");
+
+            // requirements are collected from registered resources without duplicates
+            let mock = MockBuilder::new().requirement(present.clone()).build();
+            builder.require(mock).unwrap();
+            let mock = MockBuilder::new().requirement(present.clone()).build();
+            builder.require(mock).unwrap();
+            assert_eq!(builder.requirements(), vec![present.clone()]);
+            assert!(builder.check_requirements().is_ok());
+
+            // missing tools are named in the error
+            let mock = MockBuilder::new().requirement(missing).build();
+            builder.require(mock).unwrap();
+            let error = builder.check_requirements().unwrap_err().to_string();
+            assert!(error.contains("blanket-rs-missing-tool (>=1.0)"));
+        }
+
+        #[test]
+        fn test_log_file() {
+            let path = std::env::temp_dir().join(format!("blanket-rs-log-{}", std::process::id()));
+            let _ = std::fs::remove_file(&path);
+
+            let mut builder = Builder::new();
+            builder.log_file(&path);
+            let common = Rc::new(RefCell::new(MockBuilder::new().build()));
+            let dependent = MockBuilder::new().shared(common).build();
+            builder.require(dependent).unwrap();
+            builder.generate().unwrap();
+
+            let log = std::fs::read_to_string(&path).unwrap();
+            let lines: Vec<&str> = log.lines().collect();
+            assert_eq!(lines.len(), 3);
+            assert!(lines[0].contains("generating {id: 1"));
+            assert!(lines[1].contains("generating {id: 0"));
+            assert!(lines[2].contains("generated 2 resources"));
+
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        #[test]
+        fn test_on_complete() {
+            let mut builder = Builder::new();
+            let common = Rc::new(RefCell::new(MockBuilder::new().build()));
+            let dependent = MockBuilder::new().shared(common).build();
+            builder.require(dependent).unwrap();
+
+            let completed = Rc::new(RefCell::new(vec![]));
+            let record = completed.clone();
+            builder.on_complete(move |node, _| record.borrow_mut().push(node.id));
+            builder.generate().unwrap();
+            assert_eq!(*completed.borrow(), vec![1, 0]);
+        }
+
+        #[test]
+        fn test_on_progress() {
+            let mut builder = Builder::new();
+            let failing = Rc::new(RefCell::new(MockBuilder::new().fails(true).build()));
+            let dependent = MockBuilder::new().shared(failing).build();
+            builder.require(dependent).unwrap();
+
+            // skipped resources still count towards progress
+            let progress = Rc::new(RefCell::new(vec![]));
+            let record = progress.clone();
+            builder
+                .on_progress(move |completed, total| record.borrow_mut().push((completed, total)));
+            builder.generate_all().unwrap_err();
+            assert_eq!(*progress.borrow(), vec![(1, 2), (2, 2)]);
+        }
+
+        #[test]
+        fn test_build_order() {
+            let mut builder = Builder::new();
+            let common = Rc::new(RefCell::new(MockBuilder::new().build()));
+            let dependent = MockBuilder::new().shared(common).build();
+            let node = builder.require(dependent).unwrap();
+
+            let order: Vec<Vec<u64>> = builder
+                .build_order()
+                .iter()
+                .map(|layer| layer.iter().map(|node| node.id).collect())
+                .collect();
+            assert_eq!(order, vec![vec![node.dependencies[0].id], vec![node.id]]);
+        }
+
+        #[test]
+        fn test_build_order_isolated() {
+            let mut builder = Builder::new();
+            let isolated = builder.require(MockBuilder::new().build()).unwrap();
+            let common = Rc::new(RefCell::new(MockBuilder::new().build()));
+            let dependent = MockBuilder::new().shared(common).build();
+            let node = builder.require(dependent).unwrap();
+
+            // a resource without edges is generated in the first layer
+            let order: Vec<Vec<u64>> = builder
+                .build_order()
+                .iter()
+                .map(|layer| layer.iter().map(|node| node.id).collect())
+                .collect();
+            let first = vec![isolated.id, node.dependencies[0].id];
+            assert_eq!(order, vec![first, vec![node.id]]);
+
+            // and on its own
+            let mut builder = Builder::new();
+            let failing = builder
+                .require(MockBuilder::new().fails(true).build())
+                .unwrap();
+            let errors = builder.generate_all().unwrap_err();
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].0.id, failing.id);
+        }
+
+        #[test]
+        fn test_generate_isolated() {
+            let mut builder = Builder::new();
+            builder.require(MockBuilder::new().build()).unwrap();
+            let common = Rc::new(RefCell::new(MockBuilder::new().build()));
+            let dependent = MockBuilder::new().shared(common).build();
+            builder.require(dependent).unwrap();
+
+            // the resource without edges is generated along with the others
+            let completed = Rc::new(RefCell::new(vec![]));
+            let record = completed.clone();
+            builder.on_complete(move |node, _| record.borrow_mut().push(node.id));
+            builder.generate().unwrap();
+            assert_eq!(*completed.borrow(), vec![0, 2, 1]);
+
+            // so its failure is reported
+            let mut builder = Builder::new();
+            builder
+                .require(MockBuilder::new().fails(true).build())
+                .unwrap();
+            let error = builder.generate().unwrap_err();
+            assert_eq!(error.to_string(), "mock failed");
+        }
+
+        #[test]
+        fn test_build_order_sorted() {
+            let mut builder = Builder::new();
+            for _ in 0..8 {
+                let common = Rc::new(RefCell::new(MockBuilder::new().build()));
+                builder
+                    .require(MockBuilder::new().shared(common).build())
+                    .unwrap();
+            }
+
+            // nodes within a layer are ordered by id
+            for layer in builder.build_order() {
+                let ids: Vec<u64> = layer.iter().map(|node| node.id).collect();
+                let mut sorted = ids.clone();
+                sorted.sort();
+                assert_eq!(ids, sorted);
+            }
+        }
+
+        #[test]
+        fn test_producer_required_later() {
+            let mut builder = Builder::new();
+            let reader = builder
+                .require(MockBuilder::new().reads("site/./app.js").build())
+                .unwrap();
+            assert!(reader.dependencies.is_empty());
+
+            // the edge is added once the producer is required
+            let producer = builder
+                .require(MockBuilder::new().path("site/app.js").build())
+                .unwrap();
+            let order: Vec<Vec<u64>> = builder
+                .build_order()
+                .iter()
+                .map(|layer| layer.iter().map(|node| node.id).collect())
+                .collect();
+            assert_eq!(order, vec![vec![producer.id], vec![reader.id]]);
+            assert_eq!(builder.nodes()[&reader.id].dependencies, vec![producer]);
+
+            // and resources depending on a failed producer are skipped
+            let mut builder = Builder::new();
+            builder
+                .require(MockBuilder::new().reads("site/app.js").build())
+                .unwrap();
+            builder
+                .require(MockBuilder::new().path("site/app.js").fails(true).build())
+                .unwrap();
+            let errors = builder.generate_all().unwrap_err();
+            assert_eq!(errors.len(), 2);
+            assert_eq!(errors[1].1.to_string(), "skipped as dependency 1 failed");
+        }
+
+        #[test]
+        fn test_producer_required_later_cycle() {
+            let mut builder = Builder::new();
+            let common = Rc::new(RefCell::new(
+                MockBuilder::new().reads("site/app.js").build(),
+            ));
+            builder
+                .require(
+                    MockBuilder::new()
+                        .path("site/app.js")
+                        .shared(common)
+                        .build(),
+                )
+                .unwrap_err();
+        }
+
+        #[test]
+        fn test_outputs_under() {
+            let mut builder = Builder::new();
+            builder
+                .require(MockBuilder::new().path("site/index.html").build())
+                .unwrap();
+            builder
+                .require(MockBuilder::new().path("other/a.html").build())
+                .unwrap();
+            builder
+                .require(MockBuilder::new().path("site/CNAME").build())
+                .unwrap();
+            assert_eq!(
+                builder.outputs_under("./site", |path| path.extension().is_some()),
+                vec![PathBuf::from("site/index.html")]
+            );
+
+            // outputs are not rejected when listed outside of dependencies
+            builder
+                .require(MockBuilder::new().path("site/about.html").build())
+                .unwrap();
+
+            // but are once a resource lists them
+            builder
+                .require(MockBuilder::new().lists(true).build())
+                .unwrap();
+            builder
+                .require(MockBuilder::new().path("site/CHANGES").build())
+                .unwrap();
+            let error = builder
+                .require(MockBuilder::new().path("site/late.html").build())
+                .unwrap_err();
+            assert!(error
+                .to_string()
+                .starts_with("\"site/late.html\" was required after"));
+        }
+
+        #[test]
+        fn test_cancel() {
+            let mut builder = Builder::new();
+            let common = Rc::new(RefCell::new(MockBuilder::new().build()));
+            let dependent = MockBuilder::new().shared(common).build();
+            builder.require(dependent).unwrap();
+
+            builder.cancel_token(Arc::new(AtomicBool::new(true)));
+            let error = builder.generate().unwrap_err();
+            assert!(error.downcast_ref::<Cancelled>().is_some());
+        }
+
+        #[test]
+        fn test_generate_all() {
+            let mut builder = Builder::new();
+            let failing = Rc::new(RefCell::new(MockBuilder::new().fails(true).build()));
+            let dependent = MockBuilder::new().shared(failing).build();
+            let a = builder.require(dependent).unwrap();
+            let working = Rc::new(RefCell::new(MockBuilder::new().build()));
+            let independent = MockBuilder::new().fails(true).shared(working).build();
+            let b = builder.require(independent).unwrap();
+
+            // both failures are reported and the dependent of the first is skipped
+            let errors = builder.generate_all().unwrap_err();
+            let failures: Vec<(u64, String)> = errors
+                .iter()
+                .map(|(node, error)| (node.id, error.to_string()))
+                .collect();
+            assert_eq!(failures.len(), 3);
+            let failing = a.dependencies[0].id;
+            assert!(failures.contains(&(failing, String::from("mock failed"))));
+            assert!(failures.contains(&(b.id, String::from("mock failed"))));
+            let skipped = format!("skipped as dependency {} failed", failing);
+            assert!(failures.contains(&(a.id, skipped)));
+        }
+
+        #[test]
+        fn test_clean() {
+            let root =
+                std::env::temp_dir().join(format!("blanket-rs-clean-{}", std::process::id()));
+            if root.exists() {
+                std::fs::remove_dir_all(&root).unwrap();
+            }
+            std::fs::create_dir_all(root.join("kept")).unwrap();
+            std::fs::create_dir_all(root.join("stale")).unwrap();
+            std::fs::write(root.join("index.html"), "").unwrap();
+            std::fs::write(root.join("old.html"), "").unwrap();
+            std::fs::write(root.join("kept/style.css"), "").unwrap();
+            std::fs::write(root.join("stale/image.png"), "").unwrap();
+
+            let mut builder = Builder::new();
+            for path in ["index.html", "kept/style.css"] {
+                let mock = MockBuilder::new().path(root.join(path)).build();
+                builder.require(mock).unwrap();
+            }
+            let mut expected = vec![
+                root.join("old.html"),
+                root.join("stale"),
+                root.join("stale/image.png"),
+            ];
+            expected.sort();
+
+            // a dry run reports stale paths without removing them
+            let mut stale = builder.clean_dry_run(&root).unwrap();
+            stale.sort();
+            assert_eq!(stale, expected);
+            assert!(root.join("stale/image.png").exists());
+
+            // cleaning removes stale paths and keeps registered outputs
+            let mut removed = builder.clean(&root).unwrap();
+            removed.sort();
+            assert_eq!(removed, expected);
+            assert!(!root.join("stale").exists());
+            assert!(!root.join("old.html").exists());
+            assert!(root.join("index.html").exists());
+            assert!(root.join("kept/style.css").exists());
+
+            std::fs::remove_dir_all(&root).unwrap();
+        }
     }
 }
@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+/// An external tool which must be present for a resource to generate.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Requirement {
+    pub binary: String,
+    pub version: Option<String>,
+}
+
+impl Requirement {
+    pub fn new(binary: &str) -> Self {
+        Self {
+            binary: binary.to_string(),
+            version: None,
+        }
+    }
+
+    /// Records a version constraint, e.g. `>=1.60`.
+    /// The constraint is reported alongside the binary but is not checked.
+    pub fn version(mut self, version: &str) -> Self {
+        self.version = Some(version.to_string());
+        self
+    }
+
+    /// Returns the location of the binary on `PATH`, if it can be found.
+    pub fn locate(&self) -> Option<PathBuf> {
+        let paths = std::env::var_os("PATH")?;
+        let name = format!("{}{}", self.binary, std::env::consts::EXE_SUFFIX);
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(&name))
+            .find(|candidate| candidate.is_file())
+    }
+}
+
+impl std::fmt::Display for Requirement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{} ({})", self.binary, version),
+            None => write!(f, "{}", self.binary),
+        }
+    }
+}
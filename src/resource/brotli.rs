@@ -0,0 +1,177 @@
+use brotli::CompressorWriter;
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::Directory,
+};
+
+// base 2 logarithm of the sliding window size, the format's default
+const LG_WINDOW_SIZE: u32 = 22;
+
+// window sizes allowed by the format
+const LG_WINDOW_SIZES: std::ops::RangeInclusive<u32> = 10..=24;
+
+/// Writes a Brotli-compressed copy of a file, e.g. `style.css.br` alongside
+/// `style.css`, for servers which serve precompressed assets.
+///
+/// The source is left untouched. Depends on the resource producing the
+/// source, if one is registered.
+#[derive(Debug)]
+pub struct Brotli {
+    id: Option<u64>,
+    source: PathBuf,
+    path: PathBuf,
+    quality: u32,
+    window: u32,
+}
+
+impl Brotli {
+    pub fn new<P: AsRef<Path>>(source: P, path: P) -> Self {
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
+            quality: 11,
+            window: LG_WINDOW_SIZE,
+        }
+    }
+
+    /// Sets the quality from 0 to 11, trading build time for compression
+    /// ratio. Defaults to 11. Generation fails for a quality above 11.
+    pub fn quality(mut self, quality: u32) -> Self {
+        self.quality = quality;
+        self
+    }
+
+    /// Sets the base 2 logarithm of the sliding window size from 10 to 24.
+    /// Defaults to 22. Generation fails outside of that range.
+    pub fn window(mut self, window: u32) -> Self {
+        self.window = window;
+        self
+    }
+}
+
+impl PartialEq for Brotli {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source && self.quality == other.quality && self.window == other.window
+    }
+}
+
+impl Build for Brotli {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        if let Some(node) = builder.producer(&self.source) {
+            builder.label(&node, "source");
+            dependencies.push(node);
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.quality > 11 {
+            let message = format!(
+                "invalid brotli quality {} for {:?}, expected 0 to 11",
+                self.quality, self.path
+            );
+            return Err(message.into());
+        }
+        if !LG_WINDOW_SIZES.contains(&self.window) {
+            let message = format!(
+                "invalid brotli window {} for {:?}, expected 10 to 24",
+                self.window, self.path
+            );
+            return Err(message.into());
+        }
+        let source = File::open(&self.source)
+            .map_err(|e| format!("failed to read {:?}: {}", self.source, e))?;
+        let destination = File::create(&self.path)
+            .map_err(|e| format!("failed to create {:?}: {}", self.path, e))?;
+        let mut encoder =
+            CompressorWriter::new(BufWriter::new(destination), 4096, self.quality, self.window);
+        std::io::copy(&mut BufReader::new(source), &mut encoder)
+            .map_err(|e| format!("failed to compress {:?}: {}", self.source, e))?;
+        // finishes the stream before flushing the file
+        encoder.into_inner().flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::scratch_dir;
+    use std::io::Read;
+
+    fn decompress(path: &Path) -> String {
+        let file = File::open(path).unwrap();
+        let mut decompressed = String::new();
+        brotli::Decompressor::new(file, 4096)
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        decompressed
+    }
+
+    #[test]
+    fn test_brotli() {
+        let dir = scratch_dir("brotli");
+        let text = "body { color: red; }\n".repeat(100);
+        std::fs::write(dir.join("style.css"), &text).unwrap();
+
+        // the compressed copy decompresses to the source
+        for (quality, window) in [(0, 10), (5, 22), (11, 24)] {
+            let mut brotli = Brotli::new(dir.join("style.css"), dir.join("style.css.br"))
+                .quality(quality)
+                .window(window);
+            brotli.generate().unwrap();
+            assert_eq!(decompress(&dir.join("style.css.br")), text);
+        }
+
+        // values outside the format's ranges are rejected
+        let mut brotli = Brotli::new(dir.join("style.css"), dir.join("style.css.br")).quality(12);
+        let error = brotli.generate().unwrap_err();
+        assert!(error.to_string().starts_with("invalid brotli quality 12"));
+        for window in [9, 25] {
+            let mut brotli =
+                Brotli::new(dir.join("style.css"), dir.join("style.css.br")).window(window);
+            let error = brotli.generate().unwrap_err();
+            assert!(error
+                .to_string()
+                .starts_with(&format!("invalid brotli window {}", window)));
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,160 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::Directory,
+};
+
+/// Writes the contents of several files, in order, to one file.
+///
+/// Depends on the resources producing each source, if registered. A missing
+/// source is an error naming it.
+#[derive(Debug)]
+pub struct Concat {
+    id: Option<u64>,
+    sources: Vec<PathBuf>,
+    path: PathBuf,
+    separator: String,
+}
+
+impl Concat {
+    pub fn new<P: AsRef<Path>>(sources: Vec<P>, path: P) -> Self {
+        Self {
+            id: None,
+            sources: sources
+                .iter()
+                .map(|source| source.as_ref().to_path_buf())
+                .collect(),
+            path: path.as_ref().to_path_buf(),
+            separator: String::new(),
+        }
+    }
+
+    /// Inserts `separator` between the contents of consecutive sources,
+    /// e.g. `"\n"` or `";\n"` for scripts.
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_string();
+        self
+    }
+}
+
+impl PartialEq for Concat {
+    fn eq(&self, other: &Self) -> bool {
+        self.sources == other.sources && self.separator == other.separator
+    }
+}
+
+impl Build for Concat {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        for source in &self.sources {
+            if let Some(node) = builder.producer(source) {
+                builder.label(&node, "source");
+                dependencies.push(node);
+            }
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut bundle = vec![];
+        for (index, source) in self.sources.iter().enumerate() {
+            if index > 0 {
+                bundle.extend_from_slice(self.separator.as_bytes());
+            }
+            let bytes =
+                std::fs::read(source).map_err(|e| format!("failed to read {:?}: {}", source, e))?;
+            bundle.extend_from_slice(&bytes);
+        }
+        std::fs::write(&self.path, bundle)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{scratch_dir, StringFile};
+
+    #[test]
+    fn test_concat() {
+        let dir = scratch_dir("concat");
+        std::fs::write(dir.join("b.js"), "let b = 2;").unwrap();
+        std::fs::write(dir.join("a.js"), "let a = 1;").unwrap();
+
+        // sources are joined in the order given, not by name
+        let sources = vec![dir.join("b.js"), dir.join("a.js"), dir.join("b.js")];
+        let mut concat = Concat::new(sources.clone(), dir.join("bundle.js"));
+        concat.generate().unwrap();
+        let bundle = std::fs::read_to_string(dir.join("bundle.js")).unwrap();
+        assert_eq!(bundle, "let b = 2;let a = 1;let b = 2;");
+
+        // with the separator only between sources
+        let mut concat = Concat::new(sources, dir.join("bundle.js")).separator(";\n");
+        concat.generate().unwrap();
+        let bundle = std::fs::read_to_string(dir.join("bundle.js")).unwrap();
+        assert_eq!(bundle, "let b = 2;;\nlet a = 1;;\nlet b = 2;");
+
+        // a missing source is named
+        let mut concat = Concat::new(
+            vec![dir.join("a.js"), dir.join("c.js")],
+            dir.join("bundle.js"),
+        );
+        let error = concat.generate().unwrap_err();
+        assert!(error.to_string().contains("c.js"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_concat_source_required_later() {
+        let dir = scratch_dir("concat-later");
+        std::fs::write(dir.join("a.js"), "a").unwrap();
+        let sources = vec![dir.join("a.js"), dir.join("b.js")];
+
+        // the second source is produced by a resource required afterwards
+        let mut builder = Builder::new();
+        builder
+            .require(Concat::new(sources, dir.join("bundle.js")).separator("\n"))
+            .unwrap();
+        builder
+            .require(StringFile::new(dir.join("b.js"), "b"))
+            .unwrap();
+        builder.generate().unwrap();
+
+        let bundle = std::fs::read_to_string(dir.join("bundle.js")).unwrap();
+        assert_eq!(bundle, "a\nb");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
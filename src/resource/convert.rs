@@ -0,0 +1,164 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::Directory,
+};
+
+/// Converts a JSON, YAML or TOML data file to JSON.
+///
+/// The format is detected from the source extension. Object keys are written
+/// in sorted order, so equivalent sources produce identical output.
+#[derive(Debug)]
+pub struct ConvertData {
+    id: Option<u64>,
+    source: PathBuf,
+    path: PathBuf,
+    pretty: bool,
+}
+
+impl ConvertData {
+    pub fn new<P: AsRef<Path>>(source: P, path: P) -> Self {
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
+            pretty: false,
+        }
+    }
+
+    /// Indents the output for readability.
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+}
+
+impl PartialEq for ConvertData {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source && self.pretty == other.pretty
+    }
+}
+
+impl Build for ConvertData {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        if let Some(node) = builder.producer(&self.source) {
+            builder.label(&node, "source");
+            dependencies.push(node);
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(&self.source)
+            .map_err(|e| format!("failed to read {:?}: {}", self.source, e))?;
+        let extension = self
+            .source
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        let parsed: Result<serde_json::Value, String> = match extension.as_deref() {
+            Some("json") => serde_json::from_str(&text).map_err(|e| e.to_string()),
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&text).map_err(|e| e.to_string()),
+            Some("toml") => toml::from_str(&text).map_err(|e| e.to_string()),
+            _ => {
+                let message = format!("unsupported data format {:?}", self.source);
+                return Err(message.into());
+            }
+        };
+        let value = parsed.map_err(|e| format!("failed to parse {:?}: {}", self.source, e))?;
+        let json = match self.pretty {
+            true => serde_json::to_string_pretty(&value)?,
+            false => serde_json::to_string(&value)?,
+        };
+        std::fs::write(&self.path, json)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::scratch_dir;
+
+    fn convert(dir: &Path, name: &str, text: &str) -> Result<String, String> {
+        std::fs::write(dir.join(name), text).unwrap();
+        let mut convert = ConvertData::new(dir.join(name), dir.join("data.json"));
+        convert.generate().map_err(|e| e.to_string())?;
+        Ok(std::fs::read_to_string(dir.join("data.json")).unwrap())
+    }
+
+    #[test]
+    fn test_convert_data() {
+        let dir = scratch_dir("convert");
+        let expected = r#"{"name":"blanket","tags":["site","static"],"version":2}"#;
+
+        // each format converts to the same canonical json
+        let toml = "version = 2\nname = \"blanket\"\ntags = [\"site\", \"static\"]\n";
+        assert_eq!(convert(&dir, "data.toml", toml).unwrap(), expected);
+        let yaml = "version: 2\nname: blanket\ntags:\n  - site\n  - static\n";
+        assert_eq!(convert(&dir, "data.yml", yaml).unwrap(), expected);
+        let json = r#"{"version": 2, "tags": ["site", "static"], "name": "blanket"}"#;
+        assert_eq!(convert(&dir, "DATA.JSON", json).unwrap(), expected);
+
+        // and is indented when pretty
+        std::fs::write(dir.join("small.yaml"), "a: 1\n").unwrap();
+        let mut convert =
+            ConvertData::new(dir.join("small.yaml"), dir.join("data.json")).pretty(true);
+        convert.generate().unwrap();
+        let json = std::fs::read_to_string(dir.join("data.json")).unwrap();
+        assert_eq!(json, "{\n  \"a\": 1\n}");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_convert_data_errors() {
+        let dir = scratch_dir("convert-errors");
+
+        // parse errors name the file
+        let error = convert(&dir, "bad.toml", "name = ").unwrap_err();
+        assert!(error.starts_with("failed to parse"));
+        assert!(error.contains("bad.toml"));
+        let error = convert(&dir, "bad.json", "{\"name\": ").unwrap_err();
+        assert!(error.contains("bad.json"));
+
+        // as do unknown extensions
+        let error = convert(&dir, "data.ini", "name = blanket").unwrap_err();
+        assert!(error.starts_with("unsupported data format"));
+        assert!(error.contains("data.ini"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,26 +1,27 @@
-use regex::Regex;
+use encoding_rs::{DecoderResult, Encoding};
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 use crate::{
     builder::{Build, Builder, Node, Registration},
-    resource::Directory,
+    resource::{
+        filter::{
+            build_filter, globs, regexes, relative_empty_dirs, relative_files, Filter, Pattern,
+            Walk,
+        },
+        Directory,
+    },
 };
 
-#[derive(Clone, Debug)]
-struct Filters {
-    exclude: Vec<Regex>,
-    include: Vec<Regex>,
-}
-
-type Filter = Box<dyn Fn(&String) -> bool>;
-
 #[derive(Debug)]
 pub struct CopyFile {
     id: Option<u64>,
     source: PathBuf,
     path: PathBuf,
+    encoding: Option<&'static Encoding>,
 }
 
 impl CopyFile {
@@ -29,13 +30,21 @@ impl CopyFile {
             id: None,
             source: source.as_ref().to_path_buf(),
             path: path.as_ref().to_path_buf(),
+            encoding: None,
         }
     }
+
+    /// Decodes the source from `encoding` and writes it as UTF-8.
+    /// Malformed input is reported as an error rather than replaced.
+    pub fn with_encoding(mut self, encoding: &'static Encoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
 }
 
 impl PartialEq for CopyFile {
     fn eq(&self, other: &Self) -> bool {
-        self.source == other.source
+        self.source == other.source && self.encoding == other.encoding
     }
 }
 
@@ -64,29 +73,259 @@ impl Build for CopyFile {
     ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
         let dependencies = match self.path.parent() {
             Some(parent) => {
-                vec![builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?]
-            },
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let CopyFile {
+            source,
+            path,
+            encoding,
+            ..
+        } = self;
+        if source.is_dir() {
+            return Err("source is a directory".into());
+        }
+        // the parent is usually created by the directory dependency, but not
+        // when the file is generated on its own
+        if let Some(parent) = path.parent().filter(|parent| !parent.exists()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("failed to create {:?}: {}", parent, e))?;
+        }
+        if let Some(encoding) = encoding {
+            let text = read_text(source, Some(encoding))?;
+            if std::fs::read(&path).is_ok_and(|existing| existing == text.as_bytes()) {
+                return Ok(());
+            }
+            std::fs::write(path, text)?;
+            return Ok(());
+        }
+        // leave an identical destination untouched so its modification time
+        // does not change
+        if identical(source, path)? {
+            return Ok(());
+        }
+        copy(source, path)
+    }
+}
+
+// size of the buffers used when copying, large enough to keep the number of
+// system calls low for big files
+const BUFFER_SIZE: usize = 64 * 1024;
+
+// streams `source` to `path` through buffers of `BUFFER_SIZE`, naming the
+// file at fault in any error
+pub(crate) fn copy(source: &Path, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let reader =
+        std::fs::File::open(source).map_err(|e| format!("failed to read {:?}: {}", source, e))?;
+    let writer =
+        std::fs::File::create(path).map_err(|e| format!("failed to create {:?}: {}", path, e))?;
+    let mut reader = std::io::BufReader::with_capacity(BUFFER_SIZE, reader);
+    let mut writer = std::io::BufWriter::with_capacity(BUFFER_SIZE, writer);
+    std::io::copy(&mut reader, &mut writer)
+        .map_err(|e| format!("failed to copy {:?} to {:?}: {}", source, path, e))?;
+    // a failure to write the final buffer would otherwise be lost on drop
+    writer
+        .into_inner()
+        .map_err(|e| format!("failed to write {:?}: {}", path, e.error()))?;
+    Ok(())
+}
+
+// compares the contents of two files, treating a missing destination as
+// different
+fn identical(source: &Path, path: &Path) -> std::io::Result<bool> {
+    let length = match std::fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    if std::fs::metadata(source)?.len() != length {
+        return Ok(false);
+    }
+
+    let mut source = std::io::BufReader::new(std::fs::File::open(source)?);
+    let mut path = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut remaining = length;
+    let mut source_chunk = [0; 8192];
+    let mut path_chunk = [0; 8192];
+    while remaining > 0 {
+        let size = remaining.min(source_chunk.len() as u64) as usize;
+        source.read_exact(&mut source_chunk[..size])?;
+        path.read_exact(&mut path_chunk[..size])?;
+        if source_chunk[..size] != path_chunk[..size] {
+            return Ok(false);
+        }
+        remaining -= size as u64;
+    }
+    Ok(true)
+}
+
+/// Links `path` to `source` instead of copying it.
+///
+/// Where symbolic links cannot be created, e.g. on Windows without the
+/// required privilege, the file is copied instead and a warning is logged.
+#[derive(Debug)]
+pub struct SymlinkFile {
+    id: Option<u64>,
+    source: PathBuf,
+    path: PathBuf,
+}
+
+impl SymlinkFile {
+    pub fn new<P: AsRef<Path>>(source: P, path: P) -> Self {
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl PartialEq for SymlinkFile {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Build for SymlinkFile {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
             None => vec![],
         };
         Ok(dependencies)
     }
     fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let CopyFile { source, path, .. } = self;
+        let SymlinkFile { source, path, .. } = self;
         if source.is_dir() {
             return Err("source is a directory".into());
         }
-        let mut source = std::fs::File::open(source)?;
-        let mut dest = std::fs::File::create(path)?;
-        std::io::copy(&mut source, &mut dest)?;
+
+        // links are resolved relative to their own location so the target
+        // must be absolute
+        let target = source.canonicalize()?;
+
+        // replace the link left by a previous build
+        if path.symlink_metadata().is_ok() {
+            std::fs::remove_file(&path)?;
+        }
+
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&target, &path)?;
+
+        #[cfg(windows)]
+        if let Err(error) = std::os::windows::fs::symlink_file(&target, &path) {
+            log::warn!(
+                "failed to link {:?} to {:?}, copying instead: {}",
+                path,
+                target,
+                error
+            );
+            copy(&target, path)?;
+        }
+
+        #[cfg(not(any(unix, windows)))]
+        copy(&target, path)?;
+
         Ok(())
     }
 }
 
+// decodes `bytes` read from `path` to UTF-8 without replacement
+// reads `path` as text, decoding it from `encoding` when given and
+// otherwise expecting UTF-8
+pub(crate) fn read_text(
+    path: &Path,
+    encoding: Option<&'static Encoding>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let bytes = std::fs::read(path).map_err(|e| format!("failed to read {:?}: {}", path, e))?;
+    match encoding {
+        Some(encoding) => decode(path, &bytes, encoding),
+        None => {
+            String::from_utf8(bytes).map_err(|e| format!("failed to read {:?}: {}", path, e).into())
+        }
+    }
+}
+
+fn decode(
+    path: &Path,
+    bytes: &[u8],
+    encoding: &'static Encoding,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let capacity = decoder
+        .max_utf8_buffer_length_without_replacement(bytes.len())
+        .ok_or("input too large to decode")?;
+    let mut text = String::with_capacity(capacity);
+    let (result, read) = decoder.decode_to_string_without_replacement(bytes, &mut text, true);
+    match result {
+        DecoderResult::InputEmpty => Ok(text),
+        DecoderResult::Malformed(malformed, consumed) => {
+            let offset = read - consumed as usize - malformed as usize;
+            let message = format!(
+                "invalid {} byte sequence in {:?} at byte offset {}",
+                encoding.name(),
+                path,
+                offset
+            );
+            Err(message.into())
+        }
+        DecoderResult::OutputFull => Err(format!("failed to decode {:?}", path).into()),
+    }
+}
+
+/// Copies every file under `source` to the same relative location under
+/// `path`.
+///
+/// When `path` lies inside `source` the destination tree is skipped during
+/// the walk, so previously generated output is never copied back into
+/// itself.
+///
+/// When flattened every file is copied directly into `path` by file name,
+/// and registration fails if two files share a name.
+///
+/// Empty directories are only created when preserved, and not when
+/// flattened. The same filter is applied to their relative paths as to
+/// files.
 pub struct CopyDir {
     id: Option<u64>,
     source: PathBuf,
     path: PathBuf,
     files: Vec<Rc<RefCell<CopyFile>>>,
+    directories: Vec<Rc<RefCell<Directory>>>,
+    error: Option<String>,
 }
 
 impl std::fmt::Debug for CopyDir {
@@ -100,39 +339,68 @@ impl std::fmt::Debug for CopyDir {
 
 impl CopyDir {
     pub fn new<P: AsRef<Path>>(source: P, path: P, filter: Filter) -> Self {
-        let files = walkdir::WalkDir::new(&source)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter_map(|e| {
-                let path = e.path();
-                if !path.is_file() {
-                    return None;
+        Self::walk(&CopyDirBuilder::new(source, path), filter)
+    }
+
+    fn walk(options: &CopyDirBuilder, filter: Filter) -> Self {
+        let source = options.source.as_path();
+        let path = options.path.as_path();
+
+        let walk = Walk {
+            skip: Some(path),
+            max_depth: options.max_depth,
+            follow_links: options.follow_symlinks,
+            gitignore: options.respect_gitignore,
+        };
+
+        let mut files = vec![];
+        let mut error = None;
+        let mut destinations: HashMap<PathBuf, PathBuf> = HashMap::new();
+        let relatives = relative_files(source, &walk).unwrap_or_else(|e| {
+            error = Some(e);
+            vec![]
+        });
+        for relative in relatives {
+            if !filter(&relative) {
+                continue;
+            }
+            let file_source = source.join(&relative);
+            let file_path = match (options.flatten, Path::new(&relative).file_name()) {
+                (true, Some(name)) => path.join(name),
+                _ => path.join(&relative),
+            };
+            if let Some(existing) = destinations.get(&file_path) {
+                error.get_or_insert(format!(
+                    "flattened files {:?} and {:?} collide at {:?}",
+                    existing, file_source, file_path
+                ));
+                continue;
+            }
+            destinations.insert(file_path.clone(), file_source.clone());
+            files.push(Rc::new(RefCell::new(CopyFile::new(file_source, file_path))));
+        }
+
+        let mut directories = vec![];
+        if options.preserve_empty_dirs && !options.flatten {
+            let relatives = relative_empty_dirs(source, &walk).unwrap_or_else(|e| {
+                error.get_or_insert(e);
+                vec![]
+            });
+            for relative in relatives {
+                if filter(&relative) {
+                    let directory = Directory::new(path.join(&relative));
+                    directories.push(Rc::new(RefCell::new(directory)));
                 }
-                let relative = match path.strip_prefix(&source) {
-                    Ok(rel) => rel.to_path_buf(),
-                    Err(_) => return None,
-                };
-                let relative_str = match relative.to_str() {
-                    Some(path_str) => path_str,
-                    None => return None,
-                };
-                Some(relative_str.to_string())
-            })
-            .filter(filter.as_ref())
-            .map(|relative| {
-                (
-                    source.as_ref().join(&relative),
-                    path.as_ref().join(&relative),
-                )
-            })
-            .map(|(source, path)| Rc::new(RefCell::new(CopyFile::new(source, path))))
-            .collect();
+            }
+        }
 
         Self {
             id: None,
-            source: source.as_ref().to_path_buf(),
-            path: path.as_ref().to_path_buf(),
+            source: source.to_path_buf(),
+            path: path.to_path_buf(),
             files,
+            directories,
+            error,
         }
     }
 
@@ -164,6 +432,9 @@ impl Build for CopyDir {
         self.id
     }
     fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        if let Some(error) = &self.error {
+            return Err(error.clone().into());
+        }
         self.id = Some(id);
         Ok(Registration::Virtual())
     }
@@ -171,11 +442,18 @@ impl Build for CopyDir {
         &mut self,
         builder: &mut Builder,
     ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
-        let mut dependencies = vec![
-            builder.require_ref(Rc::new(RefCell::new(Directory::new(self.path.clone()))))?
-        ];
+        let directory =
+            builder.require_ref(Rc::new(RefCell::new(Directory::new(self.path.clone()))))?;
+        builder.label(&directory, "directory");
+        let mut dependencies = vec![directory];
         for file in self.files.clone() {
             let node = builder.require_ref(file)?;
+            builder.label(&node, "file");
+            dependencies.push(node);
+        }
+        for directory in self.directories.clone() {
+            let node = builder.require_ref(directory)?;
+            builder.label(&node, "directory");
             dependencies.push(node);
         }
         Ok(dependencies)
@@ -191,8 +469,13 @@ pub struct CopyDirBuilder {
     source: PathBuf,
     path: PathBuf,
 
-    include: Option<Vec<Regex>>,
-    exclude: Option<Vec<Regex>>,
+    include: Option<Vec<Pattern>>,
+    exclude: Option<Vec<Pattern>>,
+    flatten: bool,
+    max_depth: Option<usize>,
+    preserve_empty_dirs: bool,
+    follow_symlinks: bool,
+    respect_gitignore: bool,
 
     dependencies: Vec<Node>,
 }
@@ -205,102 +488,445 @@ impl CopyDirBuilder {
             path: path.as_ref().to_path_buf(),
             include: None,
             exclude: None,
+            flatten: false,
+            max_depth: None,
+            preserve_empty_dirs: false,
+            follow_symlinks: false,
+            respect_gitignore: false,
             dependencies: vec![],
         }
     }
 
+    /// Copies only files matching one of the regexes, replacing any include
+    /// patterns given before.
     pub fn include(mut self, patterns: Vec<&str>) -> Self {
-        let regexes = patterns
-            .into_iter()
-            .map(|pattern| Regex::new(pattern).unwrap())
-            .collect();
-        self.include = Some(regexes);
+        self.include = Some(regexes(patterns));
         self
     }
 
+    /// Skips files matching one of the regexes, replacing any exclude
+    /// patterns given before.
     pub fn exclude(mut self, patterns: Vec<&str>) -> Self {
-        let regexes = patterns
-            .into_iter()
-            .map(|pattern| Regex::new(pattern).unwrap())
-            .collect();
-        self.exclude = Some(regexes);
+        self.exclude = Some(regexes(patterns));
+        self
+    }
+
+    /// Like `include`, but adds to the include patterns given before.
+    pub fn add_include(mut self, patterns: Vec<&str>) -> Self {
+        self.include
+            .get_or_insert_with(Vec::new)
+            .extend(regexes(patterns));
+        self
+    }
+
+    /// Like `exclude`, but adds to the exclude patterns given before.
+    pub fn add_exclude(mut self, patterns: Vec<&str>) -> Self {
+        self.exclude
+            .get_or_insert_with(Vec::new)
+            .extend(regexes(patterns));
+        self
+    }
+
+    /// Like `include`, but with shell-style globs such as `*.png` or
+    /// `img/**/*.{jpg,png}`.
+    pub fn include_globs(mut self, patterns: Vec<&str>) -> Self {
+        self.include = Some(globs(patterns));
+        self
+    }
+
+    /// Like `exclude`, but with shell-style globs.
+    pub fn exclude_globs(mut self, patterns: Vec<&str>) -> Self {
+        self.exclude = Some(globs(patterns));
+        self
+    }
+
+    /// Like `include_globs`, but adds to the include patterns given before,
+    /// so that globs and regexes may be combined.
+    pub fn add_include_globs(mut self, patterns: Vec<&str>) -> Self {
+        self.include
+            .get_or_insert_with(Vec::new)
+            .extend(globs(patterns));
+        self
+    }
+
+    /// Like `exclude_globs`, but adds to the exclude patterns given before.
+    pub fn add_exclude_globs(mut self, patterns: Vec<&str>) -> Self {
+        self.exclude
+            .get_or_insert_with(Vec::new)
+            .extend(globs(patterns));
+        self
+    }
+
+    /// Copies every file directly into the destination, dropping
+    /// intermediate directories.
+    pub fn flatten(mut self, flatten: bool) -> Self {
+        self.flatten = flatten;
+        self
+    }
+
+    /// Only copies files up to `max_depth` directories deep, where files
+    /// directly in the source are at depth 1.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Creates directories which are empty in the source, so that the
+    /// destination mirrors its structure.
+    pub fn preserve_empty_dirs(mut self, preserve: bool) -> Self {
+        self.preserve_empty_dirs = preserve;
+        self
+    }
+
+    /// Descends into symlinked directories. Registration fails if a link
+    /// points back to one of its own ancestors.
+    pub fn follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// Leaves out files and directories matched by `.gitignore` files found
+    /// within the source, as git would. Ignore files outside the source and
+    /// global git configuration are not consulted.
+    pub fn respect_gitignore(mut self, respect: bool) -> Self {
+        self.respect_gitignore = respect;
         self
     }
 
     pub fn build(self) -> CopyDir {
         let filter = build_filter(self.include.clone(), self.exclude.clone());
-        CopyDir::new(self.source, self.path, filter)
+        CopyDir::walk(&self, filter)
     }
 }
 
-fn build_filter(
-    filters_include: Option<Vec<Regex>>,
-    filters_exclude: Option<Vec<Regex>>,
-) -> Filter {
-    match (filters_include, filters_exclude) {
-        // both include and exclude filters are present
-        // paths are allowed by default
-        // exclude acts as a deny list
-        // include acts as an allow list with precedence over exclude
-        (Some(filters_include), Some(filters_exclude)) => {
-            let filters = Filters {
-                include: filters_include,
-                exclude: filters_exclude,
-            };
-            Box::new(move |path: &String| {
-                for item in &filters.include {
-                    if item.is_match(path) {
-                        return true;
-                    }
-                }
-                //
-                for item in &filters.exclude {
-                    if item.is_match(path) {
-                        return false;
-                    }
-                }
-                true
-            })
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::scratch_dir;
 
-        // only include filter is present
-        // paths are denied by default
-        // include acts as an allow list
-        (Some(filters_include), None) => {
-            let filters = Filters {
-                include: filters_include,
-                exclude: Vec::new(),
-            };
-            Box::new(move |path: &String| {
-                for item in &filters.include {
-                    if item.is_match(path) {
-                        return true;
-                    }
-                }
-                false
-            })
-        }
+    fn destinations(copy_dir: &CopyDir) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = copy_dir
+            .files
+            .iter()
+            .map(|file| file.borrow().path.clone())
+            .collect();
+        paths.sort();
+        paths
+    }
 
-        // only exclude filter is present
-        // paths are allowed by default
-        // exclude acts as a deny list
-        (None, Some(filters_exclude)) => {
-            let filters = Filters {
-                include: Vec::new(),
-                exclude: filters_exclude,
-            };
-            Box::new(move |path: &String| {
-                for item in &filters.exclude {
-                    if item.is_match(path) {
-                        return false;
-                    }
-                }
-                true
-            })
-        }
+    #[test]
+    fn test_copy_dir_skips_nested_destination() {
+        let source = scratch_dir("nested-destination");
+        let output = source.join("out");
+        std::fs::create_dir_all(output.join("assets")).unwrap();
+        std::fs::write(source.join("index.html"), "index").unwrap();
+        std::fs::write(output.join("index.html"), "stale").unwrap();
+        std::fs::write(output.join("assets/stale.png"), "stale").unwrap();
+
+        let copy_dir = CopyDir::builder(&source, &output).build();
+        assert_eq!(destinations(&copy_dir), vec![output.join("index.html")]);
+
+        std::fs::remove_dir_all(&source).unwrap();
+    }
+
+    #[test]
+    fn test_copy_dir_flatten() {
+        let source = scratch_dir("flatten");
+        let output = scratch_dir("flatten-out");
+        std::fs::create_dir_all(source.join("a/b")).unwrap();
+        std::fs::write(source.join("a/one.png"), "one").unwrap();
+        std::fs::write(source.join("a/b/two.png"), "two").unwrap();
+
+        // nested files are copied directly into the destination
+        let copy_dir = CopyDir::builder(&source, &output).flatten(true).build();
+        assert_eq!(
+            destinations(&copy_dir),
+            vec![output.join("one.png"), output.join("two.png")]
+        );
+        assert!(Builder::new().require(copy_dir).is_ok());
+
+        // files sharing a name collide
+        std::fs::write(source.join("a/b/one.png"), "other").unwrap();
+        let copy_dir = CopyDir::builder(&source, &output).flatten(true).build();
+        let error = Builder::new().require(copy_dir).unwrap_err().to_string();
+        assert!(error.contains("collide"));
+
+        std::fs::remove_dir_all(&source).unwrap();
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_copy_dir_globs() {
+        let source = scratch_dir("globs");
+        let output = scratch_dir("globs-out");
+        std::fs::create_dir_all(source.join("img")).unwrap();
+        std::fs::write(source.join("index.html"), "index").unwrap();
+        std::fs::write(source.join("img/one.png"), "one").unwrap();
+        std::fs::write(source.join("img/two.jpg"), "two").unwrap();
+        std::fs::write(source.join("img/draft.png"), "draft").unwrap();
+
+        // globs select files at any depth
+        let copy_dir = CopyDir::builder(&source, &output)
+            .include_globs(vec!["*.png"])
+            .build();
+        assert_eq!(
+            destinations(&copy_dir),
+            vec![output.join("img/draft.png"), output.join("img/one.png")]
+        );
+
+        // globs combine with regexes
+        let copy_dir = CopyDir::builder(&source, &output)
+            .add_exclude_globs(vec!["img/*.png"])
+            .add_exclude(vec![r"\.jpg$"])
+            .include(vec!["^img/one"])
+            .build();
+        assert_eq!(
+            destinations(&copy_dir),
+            vec![output.join("img/one.png"), output.join("index.html")]
+        );
+
+        // include and exclude replace earlier patterns
+        let copy_dir = CopyDir::builder(&source, &output)
+            .add_include_globs(vec!["*.png"])
+            .include(vec![r"\.jpg$"])
+            .build();
+        assert_eq!(destinations(&copy_dir), vec![output.join("img/two.jpg")]);
+        let copy_dir = CopyDir::builder(&source, &output)
+            .exclude(vec!["index"])
+            .exclude(vec!["png$"])
+            .build();
+        assert_eq!(
+            destinations(&copy_dir),
+            vec![output.join("img/two.jpg"), output.join("index.html")]
+        );
+
+        // and so do their glob forms
+        let copy_dir = CopyDir::builder(&source, &output)
+            .include(vec![r"\.jpg$"])
+            .include_globs(vec!["img/one.*"])
+            .build();
+        assert_eq!(destinations(&copy_dir), vec![output.join("img/one.png")]);
+        let copy_dir = CopyDir::builder(&source, &output)
+            .exclude(vec!["index"])
+            .exclude_globs(vec!["*.png"])
+            .build();
+        assert_eq!(
+            destinations(&copy_dir),
+            vec![output.join("img/two.jpg"), output.join("index.html")]
+        );
+
+        std::fs::remove_dir_all(&source).unwrap();
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_copy_dir_max_depth() {
+        let source = scratch_dir("max-depth");
+        let output = scratch_dir("max-depth-out");
+        std::fs::create_dir_all(source.join("a/b")).unwrap();
+        std::fs::write(source.join("top.txt"), "top").unwrap();
+        std::fs::write(source.join("a/middle.txt"), "middle").unwrap();
+        std::fs::write(source.join("a/b/bottom.txt"), "bottom").unwrap();
+
+        // only top level files
+        let copy_dir = CopyDir::builder(&source, &output).max_depth(1).build();
+        assert_eq!(destinations(&copy_dir), vec![output.join("top.txt")]);
+
+        // nested files keep their relative destination
+        let copy_dir = CopyDir::builder(&source, &output).max_depth(2).build();
+        assert_eq!(
+            destinations(&copy_dir),
+            vec![output.join("a/middle.txt"), output.join("top.txt")]
+        );
+
+        std::fs::remove_dir_all(&source).unwrap();
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_copy_dir_respect_gitignore() {
+        let source = scratch_dir("gitignore");
+        let output = scratch_dir("gitignore-out");
+        std::fs::create_dir_all(source.join("build")).unwrap();
+        std::fs::create_dir_all(source.join("img")).unwrap();
+        std::fs::write(source.join(".gitignore"), "*.tmp\nbuild/\n").unwrap();
+        std::fs::write(source.join("img/.gitignore"), "Thumbs.db\n").unwrap();
+        std::fs::write(source.join("index.html"), "index").unwrap();
+        std::fs::write(source.join("draft.tmp"), "draft").unwrap();
+        std::fs::write(source.join("build/cache"), "cache").unwrap();
+        std::fs::write(source.join("img/logo.png"), "logo").unwrap();
+        std::fs::write(source.join("img/Thumbs.db"), "thumbs").unwrap();
+
+        // ignore files are honored at every level
+        let copy_dir = CopyDir::builder(&source, &output)
+            .respect_gitignore(true)
+            .build();
+        assert_eq!(
+            destinations(&copy_dir),
+            vec![
+                output.join(".gitignore"),
+                output.join("img/.gitignore"),
+                output.join("img/logo.png"),
+                output.join("index.html"),
+            ]
+        );
+
+        // and not by default
+        let copy_dir = CopyDir::builder(&source, &output).build();
+        assert_eq!(destinations(&copy_dir).len(), 7);
+
+        std::fs::remove_dir_all(&source).unwrap();
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_copy_dir_preserve_empty_dirs() {
+        let source = scratch_dir("empty-dirs");
+        let output = scratch_dir("empty-dirs-out");
+        std::fs::create_dir_all(source.join(".well-known")).unwrap();
+        std::fs::create_dir_all(source.join("a/b")).unwrap();
+        std::fs::write(source.join("index.html"), "index").unwrap();
+
+        // empty directories are dropped by default
+        let copy_dir = CopyDir::builder(&source, &output).build();
+        assert!(copy_dir.directories.is_empty());
+
+        // empty directories are created when preserved
+        let copy_dir = CopyDir::builder(&source, &output)
+            .preserve_empty_dirs(true)
+            .build();
+        assert_eq!(copy_dir.directories.len(), 2);
+        let mut builder = Builder::new();
+        builder.require(copy_dir).unwrap();
+        builder.generate().unwrap();
+        assert!(output.join(".well-known").is_dir());
+        assert!(output.join("a/b").is_dir());
+        assert!(output.join("index.html").is_file());
+
+        std::fs::remove_dir_all(&source).unwrap();
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_dir_follow_symlinks() {
+        let source = scratch_dir("follow-symlinks");
+        let output = scratch_dir("follow-symlinks-out");
+        let linked = scratch_dir("follow-symlinks-linked");
+        std::fs::write(linked.join("page.html"), "page").unwrap();
+        std::os::unix::fs::symlink(&linked, source.join("content")).unwrap();
+
+        // symlinked directories are skipped by default
+        let copy_dir = CopyDir::builder(&source, &output).build();
+        assert!(destinations(&copy_dir).is_empty());
+
+        // and copied when followed
+        let copy_dir = CopyDir::builder(&source, &output)
+            .follow_symlinks(true)
+            .build();
+        assert_eq!(
+            destinations(&copy_dir),
+            vec![output.join("content/page.html")]
+        );
+
+        // a link to an ancestor is an error
+        std::os::unix::fs::symlink(&source, linked.join("loop")).unwrap();
+        let copy_dir = CopyDir::builder(&source, &output)
+            .follow_symlinks(true)
+            .build();
+        let error = Builder::new().require(copy_dir).unwrap_err().to_string();
+        assert!(error.contains("symlink cycle"));
+
+        std::fs::remove_dir_all(&source).unwrap();
+        std::fs::remove_dir_all(&output).unwrap();
+        std::fs::remove_dir_all(&linked).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_dir_gitignore_skips_ignored_dirs() {
+        let source = scratch_dir("gitignore-dirs");
+        let output = scratch_dir("gitignore-dirs-out");
+        std::fs::create_dir_all(source.join("node_modules/pkg")).unwrap();
+        std::fs::create_dir_all(source.join("img")).unwrap();
+        std::fs::write(source.join(".gitignore"), "node_modules/\n").unwrap();
+        std::fs::write(source.join("img/logo.png"), "logo").unwrap();
+        std::os::unix::fs::symlink(&source, source.join("node_modules/pkg/loop")).unwrap();
+
+        // ignored directories are not descended into, so the link back to
+        // the source within one is never followed
+        let copy_dir = CopyDir::builder(&source, &output)
+            .respect_gitignore(true)
+            .follow_symlinks(true)
+            .build();
+        assert_eq!(
+            destinations(&copy_dir),
+            vec![output.join(".gitignore"), output.join("img/logo.png")]
+        );
+
+        // and the depth limit still applies
+        let copy_dir = CopyDir::builder(&source, &output)
+            .respect_gitignore(true)
+            .max_depth(1)
+            .build();
+        assert_eq!(destinations(&copy_dir), vec![output.join(".gitignore")]);
+
+        std::fs::remove_dir_all(&source).unwrap();
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_copy_file_skips_identical() {
+        let dir = scratch_dir("identical");
+        let source = dir.join("source.txt");
+        let path = dir.join("path.txt");
+        let old = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1);
+        std::fs::write(&source, "content").unwrap();
+
+        // an identical destination is not rewritten
+        std::fs::write(&path, "content").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        CopyFile::new(&source, &path).generate().unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().modified().unwrap(), old);
+
+        // a differing destination is replaced
+        std::fs::write(&path, "stale!!").unwrap();
+        CopyFile::new(&source, &path).generate().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "content");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_copy_file_creates_parent() {
+        let dir = scratch_dir("parent");
+        let source = dir.join("source.txt");
+        let path = dir.join("a/b/path.txt");
+        std::fs::write(&source, "content").unwrap();
+
+        CopyFile::new(&source, &path).generate().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "content");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_decode() {
+        let path = Path::new("legacy.txt");
+
+        // valid shift_jis is decoded to utf-8
+        let text = decode(path, &[0x41, 0x82, 0xA0], encoding_rs::SHIFT_JIS).unwrap();
+        assert_eq!(text, "Aあ");
 
-        // no filters are present
-        // all paths are allowed
-        (None, None) => Box::new(move |_| true),
+        // malformed input is reported with its byte offset
+        let bytes = [0x41, 0x82, 0xA0, 0xFF, 0x42];
+        let result = decode(path, &bytes, encoding_rs::SHIFT_JIS);
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("\"legacy.txt\" at byte offset 3"));
     }
 }
@@ -0,0 +1,161 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::{Directory, ResizeImage},
+};
+
+// file names and sizes of the generated icons
+const ICONS: [(&str, u32); 6] = [
+    ("favicon.ico", 32),
+    ("favicon-16x16.png", 16),
+    ("favicon-32x32.png", 32),
+    ("apple-touch-icon.png", 180),
+    ("android-chrome-192x192.png", 192),
+    ("android-chrome-512x512.png", 512),
+];
+
+/// Writes the standard set of favicons resized from one image, along with a
+/// `site.webmanifest` listing the Android icons.
+///
+/// The source should be square and at least 512 pixels wide, as icons are
+/// resized with `ResizeImage`, which preserves the aspect ratio and never
+/// enlarges. `link_tags` returns the tags to paste into `<head>`.
+#[derive(Debug)]
+pub struct Favicons {
+    id: Option<u64>,
+    source: PathBuf,
+    path: PathBuf,
+    name: String,
+    href_prefix: String,
+    icons: Vec<Rc<RefCell<ResizeImage>>>,
+}
+
+impl Favicons {
+    pub fn new<P: AsRef<Path>>(source: P, path: P) -> Self {
+        let icons = ICONS
+            .iter()
+            .map(|(name, size)| {
+                let icon = ResizeImage::new(source.as_ref(), &path.as_ref().join(name))
+                    .max_width(*size)
+                    .max_height(*size);
+                Rc::new(RefCell::new(icon))
+            })
+            .collect();
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
+            name: String::new(),
+            href_prefix: String::from("/"),
+            icons,
+        }
+    }
+
+    /// Sets the site name written to the manifest.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Sets the URL prefix of the icons as served, `/` by default.
+    pub fn href_prefix(mut self, href_prefix: &str) -> Self {
+        self.href_prefix = href_prefix.to_string();
+        self
+    }
+
+    /// Returns the `<link>` tags referencing the icons and the manifest.
+    pub fn link_tags(&self) -> String {
+        let href = |name: &str| format!("{}{}", self.href_prefix, name);
+        [
+            format!(
+                "<link rel=\"icon\" href=\"{}\" sizes=\"any\">",
+                href("favicon.ico")
+            ),
+            format!(
+                "<link rel=\"icon\" type=\"image/png\" sizes=\"32x32\" href=\"{}\">",
+                href("favicon-32x32.png")
+            ),
+            format!(
+                "<link rel=\"icon\" type=\"image/png\" sizes=\"16x16\" href=\"{}\">",
+                href("favicon-16x16.png")
+            ),
+            format!(
+                "<link rel=\"apple-touch-icon\" sizes=\"180x180\" href=\"{}\">",
+                href("apple-touch-icon.png")
+            ),
+            format!(
+                "<link rel=\"manifest\" href=\"{}\">",
+                href("site.webmanifest")
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+impl PartialEq for Favicons {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+            && self.name == other.name
+            && self.href_prefix == other.href_prefix
+    }
+}
+
+impl Build for Favicons {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.join("site.webmanifest")))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let directory = builder.require_ref(Rc::new(RefCell::new(Directory::new(&self.path))))?;
+        builder.label(&directory, "directory");
+        let mut dependencies = vec![directory];
+        for icon in self.icons.clone() {
+            let node = builder.require_ref(icon)?;
+            builder.label(&node, "icon");
+            dependencies.push(node);
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let icons: Vec<serde_json::Value> = ICONS
+            .iter()
+            .filter(|(name, _)| name.starts_with("android-chrome"))
+            .map(|(name, size)| {
+                serde_json::json!({
+                    "src": format!("{}{}", self.href_prefix, name),
+                    "sizes": format!("{}x{}", size, size),
+                    "type": "image/png",
+                })
+            })
+            .collect();
+        let manifest = serde_json::json!({
+            "name": self.name,
+            "short_name": self.name,
+            "icons": icons,
+            "display": "standalone",
+        });
+        let path = self.path.join("site.webmanifest");
+        std::fs::write(&path, serde_json::to_string_pretty(&manifest)?)?;
+        Ok(())
+    }
+}
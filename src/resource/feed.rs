@@ -0,0 +1,286 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::{xml::escape, Directory},
+};
+
+/// An entry in a `Feed`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeedItem {
+    title: String,
+    link: String,
+    date: SystemTime,
+    description: Option<String>,
+    source: Option<PathBuf>,
+}
+
+impl FeedItem {
+    pub fn new(title: &str, link: &str, date: SystemTime) -> Self {
+        Self {
+            title: title.to_string(),
+            link: link.to_string(),
+            date,
+            description: None,
+            source: None,
+        }
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Depends on the resource producing `source`, e.g. the page the item
+    /// links to, so the feed is generated after it.
+    pub fn source<P: AsRef<Path>>(mut self, source: P) -> Self {
+        self.source = Some(source.as_ref().to_path_buf());
+        self
+    }
+}
+
+/// Writes an RSS 2.0 feed of the given items, newest first.
+#[derive(Debug)]
+pub struct Feed {
+    id: Option<u64>,
+    path: PathBuf,
+    title: String,
+    link: String,
+    description: String,
+    items: Vec<FeedItem>,
+}
+
+impl Feed {
+    pub fn new<P: AsRef<Path>>(path: P, title: &str, link: &str) -> Self {
+        Self {
+            id: None,
+            path: path.as_ref().to_path_buf(),
+            title: title.to_string(),
+            link: link.to_string(),
+            description: String::new(),
+            items: vec![],
+        }
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+
+    pub fn item(mut self, item: FeedItem) -> Self {
+        self.items.push(item);
+        self
+    }
+}
+
+impl PartialEq for Feed {
+    fn eq(&self, other: &Self) -> bool {
+        self.title == other.title
+            && self.link == other.link
+            && self.description == other.description
+            && self.items == other.items
+    }
+}
+
+impl Build for Feed {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        for source in self.items.iter().filter_map(|item| item.source.as_ref()) {
+            if let Some(node) = builder.producer(source) {
+                builder.label(&node, "item");
+                dependencies.push(node);
+            }
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut items: Vec<&FeedItem> = self.items.iter().collect();
+        items.sort_by_key(|item| std::cmp::Reverse(item.date));
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<rss version=\"2.0\">\n<channel>\n");
+        xml.push_str(&format!("  <title>{}</title>\n", escape(&self.title)));
+        xml.push_str(&format!("  <link>{}</link>\n", escape(&self.link)));
+        xml.push_str(&format!(
+            "  <description>{}</description>\n",
+            escape(&self.description)
+        ));
+        for item in items {
+            xml.push_str("  <item>\n");
+            xml.push_str(&format!("    <title>{}</title>\n", escape(&item.title)));
+            xml.push_str(&format!("    <link>{}</link>\n", escape(&item.link)));
+            xml.push_str(&format!("    <guid>{}</guid>\n", escape(&item.link)));
+            xml.push_str(&format!("    <pubDate>{}</pubDate>\n", rfc822(item.date)));
+            if let Some(description) = &item.description {
+                xml.push_str(&format!(
+                    "    <description>{}</description>\n",
+                    escape(description)
+                ));
+            }
+            xml.push_str("  </item>\n");
+        }
+        xml.push_str("</channel>\n</rss>\n");
+        std::fs::write(&self.path, xml)?;
+        Ok(())
+    }
+}
+
+// formats a time as an RFC 822 date in GMT, e.g. `Thu, 01 Jan 1970 00:00:00 GMT`
+fn rfc822(time: SystemTime) -> String {
+    const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    };
+    let days = seconds.div_euclid(86400);
+    let time_of_day = seconds.rem_euclid(86400);
+
+    // converts days since the epoch to a civil date, after Howard Hinnant's
+    // days_from_civil algorithm
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        DAYS[days.rem_euclid(7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        time_of_day / 3600,
+        time_of_day % 3600 / 60,
+        time_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{scratch_dir, StringFile};
+    use std::time::Duration;
+
+    #[test]
+    fn test_rfc822() {
+        assert_eq!(rfc822(UNIX_EPOCH), "Thu, 01 Jan 1970 00:00:00 GMT");
+
+        // a leap day
+        let time = UNIX_EPOCH + Duration::from_secs(951_827_696);
+        assert_eq!(rfc822(time), "Tue, 29 Feb 2000 12:34:56 GMT");
+
+        // before the epoch
+        let time = UNIX_EPOCH - Duration::from_secs(1);
+        assert_eq!(rfc822(time), "Wed, 31 Dec 1969 23:59:59 GMT");
+    }
+
+    #[test]
+    fn test_feed() {
+        let dir = scratch_dir("feed");
+        let older = FeedItem::new("First & best", "https://example.com/first", UNIX_EPOCH)
+            .description("<p>hello</p>");
+        let newer = FeedItem::new(
+            "Second",
+            "https://example.com/second",
+            UNIX_EPOCH + Duration::from_secs(86400),
+        );
+        let mut feed = Feed::new(dir.join("feed.xml"), "Blog", "https://example.com")
+            .description("Posts")
+            .item(older)
+            .item(newer);
+        feed.generate().unwrap();
+
+        // items are written newest first and text is escaped
+        let xml = std::fs::read_to_string(dir.join("feed.xml")).unwrap();
+        assert_eq!(
+            xml,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <rss version=\"2.0\">\n<channel>\n\
+            \x20 <title>Blog</title>\n\
+            \x20 <link>https://example.com</link>\n\
+            \x20 <description>Posts</description>\n\
+            \x20 <item>\n\
+            \x20   <title>Second</title>\n\
+            \x20   <link>https://example.com/second</link>\n\
+            \x20   <guid>https://example.com/second</guid>\n\
+            \x20   <pubDate>Fri, 02 Jan 1970 00:00:00 GMT</pubDate>\n\
+            \x20 </item>\n\
+            \x20 <item>\n\
+            \x20   <title>First &amp; best</title>\n\
+            \x20   <link>https://example.com/first</link>\n\
+            \x20   <guid>https://example.com/first</guid>\n\
+            \x20   <pubDate>Thu, 01 Jan 1970 00:00:00 GMT</pubDate>\n\
+            \x20   <description>&lt;p&gt;hello&lt;/p&gt;</description>\n\
+            \x20 </item>\n\
+             </channel>\n</rss>\n"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_feed_source_required_later() {
+        let dir = scratch_dir("feed-later");
+        let item = FeedItem::new("Post", "https://example.com/post", UNIX_EPOCH)
+            .source(dir.join("post.html"));
+        let feed = Feed::new(dir.join("feed.xml"), "Blog", "https://example.com").item(item);
+
+        // the page is produced by a resource required after the feed
+        let mut builder = Builder::new();
+        let feed = builder.require(feed).unwrap();
+        let page = builder
+            .require(StringFile::new(dir.join("post.html"), "post"))
+            .unwrap();
+        let dependencies = &builder.nodes()[&feed.id].dependencies;
+        assert!(dependencies.contains(&page));
+        builder.generate().unwrap();
+        assert!(dir.join("feed.xml").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
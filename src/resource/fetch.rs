@@ -0,0 +1,204 @@
+use sha2::Digest;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::Directory,
+};
+
+/// Downloads a remote file, e.g. a vendored script from a CDN.
+///
+/// With a cache file the `ETag` and `Last-Modified` validators of the last
+/// download are remembered, and an unchanged remote file is not downloaded
+/// again. The cache file should live outside directories passed to
+/// `Builder::clean`, which would remove it as an unregistered output.
+///
+/// A request which takes longer than the timeout, 30 seconds by default,
+/// fails so that a stalled server cannot hang the build.
+#[derive(Debug)]
+pub struct Fetch {
+    id: Option<u64>,
+    url: String,
+    path: PathBuf,
+    cache: Option<PathBuf>,
+    sha256: Option<String>,
+    timeout: Duration,
+}
+
+impl Fetch {
+    pub fn new<P: AsRef<Path>>(url: &str, path: P) -> Self {
+        Self {
+            id: None,
+            url: url.to_string(),
+            path: path.as_ref().to_path_buf(),
+            cache: None,
+            sha256: None,
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Remembers the validators of the last download in `cache`.
+    pub fn cache<P: AsRef<Path>>(mut self, cache: P) -> Self {
+        self.cache = Some(cache.as_ref().to_path_buf());
+        self
+    }
+
+    /// Fails unless the file has the given hex encoded SHA-256 digest.
+    pub fn expect_sha256(mut self, sha256: &str) -> Self {
+        self.sha256 = Some(sha256.to_ascii_lowercase());
+        self
+    }
+
+    /// Sets the time allowed for the whole request, including reading the
+    /// response body.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    // reads the validators of the last download, if the output still exists
+    fn validators(&self) -> Option<serde_json::Value> {
+        let cache = self.cache.as_ref()?;
+        if !self.path.is_file() {
+            return None;
+        }
+        let text = std::fs::read_to_string(cache).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    fn verify(&self, bytes: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(expected) = &self.sha256 {
+            let actual: String = sha2::Sha256::digest(bytes)
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            if &actual != expected {
+                let message = format!("{} has SHA-256 {}, expected {}", self.url, actual, expected);
+                return Err(message.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PartialEq for Fetch {
+    fn eq(&self, other: &Self) -> bool {
+        self.url == other.url && self.sha256 == other.sha256
+    }
+}
+
+impl Build for Fetch {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                Ok(vec![directory])
+            }
+            None => Ok(vec![]),
+        }
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_global(Some(self.timeout))
+            .build()
+            .into();
+        let mut request = agent.get(&self.url);
+        if let Some(validators) = self.validators() {
+            if let Some(etag) = validators["etag"].as_str() {
+                request = request.header("If-None-Match", etag);
+            }
+            if let Some(last_modified) = validators["last_modified"].as_str() {
+                request = request.header("If-Modified-Since", last_modified);
+            }
+        }
+        let mut response = request
+            .call()
+            .map_err(|e| format!("failed to fetch {}: {}", self.url, e))?;
+
+        if response.status() == 304 {
+            let bytes = std::fs::read(&self.path)
+                .map_err(|e| format!("failed to read {:?}: {}", self.path, e))?;
+            return self.verify(&bytes);
+        }
+
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        let validators = serde_json::json!({
+            "etag": header("etag"),
+            "last_modified": header("last-modified"),
+        });
+        let bytes = response
+            .body_mut()
+            .with_config()
+            .limit(u64::MAX)
+            .read_to_vec()
+            .map_err(|e| format!("failed to fetch {}: {}", self.url, e))?;
+        self.verify(&bytes)?;
+
+        std::fs::write(&self.path, &bytes)?;
+        if let Some(cache) = &self.cache {
+            std::fs::write(cache, validators.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::scratch_dir;
+
+    #[test]
+    fn test_fetch_timeout() {
+        // a server which accepts the connection but never responds
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/script.js", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            std::thread::sleep(Duration::from_millis(500));
+            drop(stream);
+        });
+
+        let dir = scratch_dir("fetch-timeout");
+        let mut fetch = Fetch::new(&url, dir.join("script.js")).timeout(Duration::from_millis(100));
+        let error = fetch.generate().unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with(&format!("failed to fetch {}", url)));
+        assert!(!dir.join("script.js").exists());
+
+        server.join().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
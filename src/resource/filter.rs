@@ -0,0 +1,261 @@
+use globset::{Glob, GlobMatcher};
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+/// A pattern matched against relative paths, either a regex or a
+/// shell-style glob such as `*.png`.
+#[derive(Clone, Debug)]
+pub(crate) enum Pattern {
+    Regex(Regex),
+    Glob(GlobMatcher),
+}
+
+impl Pattern {
+    fn is_match(&self, path: &str) -> bool {
+        match self {
+            Pattern::Regex(regex) => regex.is_match(path),
+            Pattern::Glob(glob) => glob.is_match(path),
+        }
+    }
+}
+
+/// Compiles regexes into patterns, panicking on an invalid regex.
+pub(crate) fn regexes(patterns: Vec<&str>) -> Vec<Pattern> {
+    patterns
+        .into_iter()
+        .map(|pattern| Pattern::Regex(Regex::new(pattern).unwrap()))
+        .collect()
+}
+
+/// Compiles shell-style globs into patterns, panicking on an invalid glob.
+pub(crate) fn globs(patterns: Vec<&str>) -> Vec<Pattern> {
+    patterns
+        .into_iter()
+        .map(|pattern| Pattern::Glob(Glob::new(pattern).unwrap().compile_matcher()))
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+struct Filters {
+    exclude: Vec<Pattern>,
+    include: Vec<Pattern>,
+}
+
+pub(crate) type Filter = Box<dyn Fn(&String) -> bool>;
+
+pub(crate) fn build_filter(
+    filters_include: Option<Vec<Pattern>>,
+    filters_exclude: Option<Vec<Pattern>>,
+) -> Filter {
+    match (filters_include, filters_exclude) {
+        // both include and exclude filters are present
+        // paths are allowed by default
+        // exclude acts as a deny list
+        // include acts as an allow list with precedence over exclude
+        (Some(filters_include), Some(filters_exclude)) => {
+            let filters = Filters {
+                include: filters_include,
+                exclude: filters_exclude,
+            };
+            Box::new(move |path: &String| {
+                for item in &filters.include {
+                    if item.is_match(path) {
+                        return true;
+                    }
+                }
+                //
+                for item in &filters.exclude {
+                    if item.is_match(path) {
+                        return false;
+                    }
+                }
+                true
+            })
+        }
+
+        // only include filter is present
+        // paths are denied by default
+        // include acts as an allow list
+        (Some(filters_include), None) => {
+            let filters = Filters {
+                include: filters_include,
+                exclude: Vec::new(),
+            };
+            Box::new(move |path: &String| {
+                for item in &filters.include {
+                    if item.is_match(path) {
+                        return true;
+                    }
+                }
+                false
+            })
+        }
+
+        // only exclude filter is present
+        // paths are allowed by default
+        // exclude acts as a deny list
+        (None, Some(filters_exclude)) => {
+            let filters = Filters {
+                include: Vec::new(),
+                exclude: filters_exclude,
+            };
+            Box::new(move |path: &String| {
+                for item in &filters.exclude {
+                    if item.is_match(path) {
+                        return false;
+                    }
+                }
+                true
+            })
+        }
+
+        // no filters are present
+        // all paths are allowed
+        (None, None) => Box::new(move |_| true),
+    }
+}
+
+/// Options for walking a source directory.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Walk<'a> {
+    /// Directory which is not descended into, if it exists.
+    pub skip: Option<&'a Path>,
+    /// Deepest directory walked, where files directly in the source are at
+    /// depth 1.
+    pub max_depth: Option<usize>,
+    /// Descends into symlinked directories. A link back to one of its own
+    /// ancestors is reported as an error.
+    pub follow_links: bool,
+    /// Leaves out paths matched by `.gitignore` files within the source.
+    pub gitignore: bool,
+}
+
+/// Returns the paths of all files under `source`, relative to `source`.
+pub(crate) fn relative_files<P: AsRef<Path>>(
+    source: P,
+    walk: &Walk,
+) -> Result<Vec<String>, String> {
+    let files = entries(&source, walk)?
+        .into_iter()
+        .filter(|e| e.path.is_file())
+        .filter_map(|e| relative(&source, &e.path))
+        .collect();
+    Ok(files)
+}
+
+/// Returns the paths of all empty directories under `source`, relative to
+/// `source`.
+pub(crate) fn relative_empty_dirs<P: AsRef<Path>>(
+    source: P,
+    walk: &Walk,
+) -> Result<Vec<String>, String> {
+    let directories = entries(&source, walk)?
+        .into_iter()
+        .filter(|e| e.depth > 0 && e.path.is_dir())
+        .filter(|e| match std::fs::read_dir(&e.path) {
+            Ok(mut children) => children.next().is_none(),
+            Err(_) => false,
+        })
+        .filter_map(|e| relative(&source, &e.path))
+        .collect();
+    Ok(directories)
+}
+
+struct Entry {
+    path: PathBuf,
+    depth: usize,
+}
+
+fn entries<P: AsRef<Path>>(source: P, walk: &Walk) -> Result<Vec<Entry>, String> {
+    // the skipped directory may not exist yet, in which case it cannot
+    // contain any files
+    let skip = walk.skip.and_then(|skip| skip.canonicalize().ok());
+    let skipped = move |path: &Path| match &skip {
+        Some(skip) => path.canonicalize().ok().as_ref() == Some(skip),
+        None => false,
+    };
+    match walk.gitignore {
+        true => not_ignored(source, walk, skipped),
+        false => walked(source, walk, skipped),
+    }
+}
+
+fn walked<P, F>(source: P, walk: &Walk, skipped: F) -> Result<Vec<Entry>, String>
+where
+    P: AsRef<Path>,
+    F: Fn(&Path) -> bool,
+{
+    let mut walker = walkdir::WalkDir::new(&source).follow_links(walk.follow_links);
+    if let Some(max_depth) = walk.max_depth {
+        walker = walker.max_depth(max_depth);
+    }
+    let mut entries = vec![];
+    let iter = walker
+        .into_iter()
+        .filter_entry(|e| !e.file_type().is_dir() || !skipped(e.path()));
+    for entry in iter {
+        match entry {
+            Ok(entry) => entries.push(Entry {
+                depth: entry.depth(),
+                path: entry.into_path(),
+            }),
+            // walkdir tracks the ancestors of each directory it descends into
+            // and reports a link back to one of them rather than looping
+            Err(e) if e.loop_ancestor().is_some() => {
+                return Err(format!("symlink cycle under {:?}: {}", source.as_ref(), e));
+            }
+            // unreadable entries are skipped
+            Err(_) => {}
+        }
+    }
+    Ok(entries)
+}
+
+// walks `source` with the `ignore` crate, which does not descend into
+// directories matched by a `.gitignore` within `source`
+fn not_ignored<P, F>(source: P, walk: &Walk, skipped: F) -> Result<Vec<Entry>, String>
+where
+    P: AsRef<Path>,
+    F: Fn(&Path) -> bool + Send + Sync + 'static,
+{
+    let walker = ignore::WalkBuilder::new(&source)
+        .standard_filters(false)
+        .git_ignore(true)
+        .require_git(false)
+        .parents(false)
+        .follow_links(walk.follow_links)
+        .max_depth(walk.max_depth)
+        .filter_entry(move |e| !e.file_type().is_some_and(|t| t.is_dir()) || !skipped(e.path()))
+        .build();
+    let mut entries = vec![];
+    for entry in walker {
+        match entry {
+            Ok(entry) => entries.push(Entry {
+                depth: entry.depth(),
+                path: entry.into_path(),
+            }),
+            // like walkdir, links back to an ancestor are reported
+            Err(e) if is_loop(&e) => {
+                return Err(format!("symlink cycle under {:?}: {}", source.as_ref(), e));
+            }
+            // unreadable entries are skipped
+            Err(_) => {}
+        }
+    }
+    Ok(entries)
+}
+
+fn is_loop(error: &ignore::Error) -> bool {
+    match error {
+        ignore::Error::Loop { .. } => true,
+        ignore::Error::WithPath { err, .. }
+        | ignore::Error::WithDepth { err, .. }
+        | ignore::Error::WithLineNumber { err, .. } => is_loop(err),
+        _ => false,
+    }
+}
+
+fn relative<P: AsRef<Path>>(source: P, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(&source).ok()?;
+    Some(relative.to_str()?.to_string())
+}
@@ -0,0 +1,206 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::{copy::copy, Directory},
+};
+
+/// Copies a file to a destination whose name includes a hash of its
+/// content, e.g. `style.css` is written as `style.1a2b3c4d.css`, so that
+/// it can be cached indefinitely.
+///
+/// The source may be produced by another resource, so it is hashed when
+/// the resource is generated. The output is registered under the logical
+/// path, and the fingerprinted path is known only once generated or, for
+/// `Builder::clean`, once the source exists.
+#[derive(Debug)]
+pub struct Fingerprint {
+    id: Option<u64>,
+    source: PathBuf,
+    path: PathBuf,
+    fingerprinted: Option<PathBuf>,
+}
+
+impl Fingerprint {
+    pub fn new<P: AsRef<Path>>(source: P, path: P) -> Self {
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
+            fingerprinted: None,
+        }
+    }
+
+    /// Returns the destination path without the hash.
+    pub fn logical(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the destination path including the hash once the resource
+    /// has been generated.
+    pub fn fingerprinted(&self) -> Option<&Path> {
+        self.fingerprinted.as_deref()
+    }
+}
+
+impl PartialEq for Fingerprint {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source && self.path == other.path
+    }
+}
+
+impl Build for Fingerprint {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        if let Some(node) = builder.producer(&self.source) {
+            builder.label(&node, "source");
+            dependencies.push(node);
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let hash = hash(&self.source)?;
+        let fingerprinted = fingerprint(&self.path, &hash);
+        copy(&self.source, &fingerprinted)?;
+        self.fingerprinted = Some(fingerprinted);
+        Ok(())
+    }
+    fn unregistered_outputs(&self) -> Vec<PathBuf> {
+        match &self.fingerprinted {
+            Some(fingerprinted) => vec![fingerprinted.clone()],
+            None => match hash(&self.source) {
+                Ok(hash) => vec![fingerprint(&self.path, &hash)],
+                Err(_) => vec![],
+            },
+        }
+    }
+}
+
+// hashes the content of the source file
+fn hash(source: &Path) -> Result<blake3::Hash, Box<dyn std::error::Error>> {
+    let file =
+        std::fs::File::open(source).map_err(|e| format!("failed to read {:?}: {}", source, e))?;
+    let hash = blake3::Hasher::new()
+        .update_reader(file)
+        .map_err(|e| format!("failed to read {:?}: {}", source, e))?
+        .finalize();
+    Ok(hash)
+}
+
+// splices the first eight hex digits of the content hash before the extension
+fn fingerprint(path: &Path, hash: &blake3::Hash) -> PathBuf {
+    let hash = hash.to_hex();
+    let hash = &hash.as_str()[..8];
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(extension) => format!("{}.{}.{}", stem, hash, extension.to_string_lossy()),
+        None => format!("{}.{}", stem, hash),
+    };
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{scratch_dir, StringFile};
+
+    #[test]
+    fn test_fingerprint_name() {
+        let hash = blake3::hash(b"body {}");
+        let prefix = &hash.to_hex()[..8];
+        assert_eq!(
+            fingerprint(Path::new("css/style.css"), &hash),
+            PathBuf::from(format!("css/style.{}.css", prefix))
+        );
+        assert_eq!(
+            fingerprint(Path::new("LICENSE"), &hash),
+            PathBuf::from(format!("LICENSE.{}", prefix))
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_produced_source() {
+        let dir = scratch_dir("fingerprint-produced");
+        let source = dir.join("build/style.css");
+        let path = dir.join("site/style.css");
+
+        // the source does not exist until its producer is generated
+        let mut builder = Builder::new();
+        builder
+            .require(StringFile::new(&source, "body {}"))
+            .unwrap();
+        let asset = Rc::new(RefCell::new(Fingerprint::new(&source, &path)));
+        builder.require_ref(asset.clone()).unwrap();
+        builder.generate().unwrap();
+
+        let fingerprinted = fingerprint(&path, &blake3::hash(b"body {}"));
+        assert_eq!(
+            asset.borrow().fingerprinted(),
+            Some(fingerprinted.as_path())
+        );
+        assert_eq!(std::fs::read_to_string(&fingerprinted).unwrap(), "body {}");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_fingerprint_clean() {
+        let dir = scratch_dir("fingerprint-clean");
+        let source = dir.join("style.css");
+        let site = dir.join("site");
+        std::fs::write(&source, "body {}").unwrap();
+        let fingerprinted = fingerprint(&site.join("style.css"), &blake3::hash(b"body {}"));
+
+        let mut builder = Builder::new();
+        builder
+            .require(Fingerprint::new(&source, &site.join("style.css")))
+            .unwrap();
+        builder.generate().unwrap();
+        std::fs::write(site.join("style.00000000.css"), "body {}").unwrap();
+
+        // the next build keeps the current fingerprint and removes older ones
+        let mut builder = Builder::new();
+        builder
+            .require(Fingerprint::new(&source, &site.join("style.css")))
+            .unwrap();
+        let removed = builder.clean(&site).unwrap();
+        assert_eq!(removed, vec![site.join("style.00000000.css")]);
+        assert!(fingerprinted.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,159 @@
+use flate2::{write::GzEncoder, Compression};
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::Directory,
+};
+
+/// Writes a gzip-compressed copy of a file, e.g. `style.css.gz` alongside
+/// `style.css`, for servers which serve precompressed assets.
+///
+/// The source is left untouched. Depends on the resource producing the
+/// source, if one is registered.
+#[derive(Debug)]
+pub struct Gzip {
+    id: Option<u64>,
+    source: PathBuf,
+    path: PathBuf,
+    level: u32,
+}
+
+impl Gzip {
+    pub fn new<P: AsRef<Path>>(source: P, path: P) -> Self {
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
+            level: Compression::best().level(),
+        }
+    }
+
+    /// Sets the compression level from 0 to 9. Defaults to 9. Generation
+    /// fails for a level above 9.
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = level;
+        self
+    }
+}
+
+impl PartialEq for Gzip {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source && self.level == other.level
+    }
+}
+
+impl Build for Gzip {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        if let Some(node) = builder.producer(&self.source) {
+            builder.label(&node, "source");
+            dependencies.push(node);
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.level > 9 {
+            let message = format!(
+                "invalid gzip level {} for {:?}, expected 0 to 9",
+                self.level, self.path
+            );
+            return Err(message.into());
+        }
+        let source = File::open(&self.source)
+            .map_err(|e| format!("failed to read {:?}: {}", self.source, e))?;
+        let destination = File::create(&self.path)
+            .map_err(|e| format!("failed to create {:?}: {}", self.path, e))?;
+        let mut encoder = GzEncoder::new(BufWriter::new(destination), Compression::new(self.level));
+        std::io::copy(&mut BufReader::new(source), &mut encoder)
+            .map_err(|e| format!("failed to compress {:?}: {}", self.source, e))?;
+        encoder.finish()?.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{scratch_dir, CopyFile};
+    use std::io::Read;
+
+    #[test]
+    fn test_gzip() {
+        let dir = scratch_dir("gzip");
+        let text = "body { color: red; }\n".repeat(100);
+        std::fs::write(dir.join("style.css"), &text).unwrap();
+
+        // the compressed copy decompresses to the source at every level
+        for level in [0, 1, 9] {
+            let mut gzip = Gzip::new(dir.join("style.css"), dir.join("style.css.gz")).level(level);
+            gzip.generate().unwrap();
+            let file = File::open(dir.join("style.css.gz")).unwrap();
+            let mut decompressed = String::new();
+            flate2::read::GzDecoder::new(file)
+                .read_to_string(&mut decompressed)
+                .unwrap();
+            assert_eq!(decompressed, text);
+        }
+
+        // levels above 9 are rejected
+        let mut gzip = Gzip::new(dir.join("style.css"), dir.join("style.css.gz")).level(10);
+        let error = gzip.generate().unwrap_err();
+        assert!(error.to_string().starts_with("invalid gzip level 10"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_gzip_source_required_later() {
+        let dir = scratch_dir("gzip-later");
+        std::fs::write(dir.join("app.js"), "let answer = 42;").unwrap();
+        let output = dir.join("out");
+        let mut builder = Builder::new();
+        builder
+            .require(Gzip::new(output.join("app.js"), output.join("app.js.gz")))
+            .unwrap();
+        builder
+            .require(CopyFile::new(dir.join("app.js"), output.join("app.js")))
+            .unwrap();
+
+        // the copy is still made before it is compressed
+        builder.generate().unwrap();
+        assert!(output.join("app.js.gz").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+use base64::Engine;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::Variable,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Encoding {
+    DataUri,
+    Text,
+}
+
+/// Reads a file during generation so its content can be embedded in another
+/// output, either as a base64 `data:` URI or as raw text.
+///
+/// Depends on the resource producing the file, if one is registered.
+/// Resources which embed the content should hold a shared reference and
+/// depend on this resource, e.g. `RenderTemplate::variable`.
+#[derive(Debug)]
+pub struct Inline {
+    id: Option<u64>,
+    source: PathBuf,
+    encoding: Encoding,
+    mime_type: Option<String>,
+    max_bytes: Option<u64>,
+    content: Option<String>,
+}
+
+impl Inline {
+    /// Inlines the file as a base64 `data:` URI.
+    pub fn data_uri<P: AsRef<Path>>(source: P) -> Self {
+        Self::new(source, Encoding::DataUri)
+    }
+
+    /// Inlines the file as text. The file must be valid UTF-8.
+    pub fn text<P: AsRef<Path>>(source: P) -> Self {
+        Self::new(source, Encoding::Text)
+    }
+
+    fn new<P: AsRef<Path>>(source: P, encoding: Encoding) -> Self {
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            encoding,
+            mime_type: None,
+            max_bytes: None,
+            content: None,
+        }
+    }
+
+    /// Sets the MIME type of a data URI. By default it is guessed from the
+    /// file extension.
+    pub fn mime_type(mut self, mime_type: &str) -> Self {
+        self.mime_type = Some(mime_type.to_string());
+        self
+    }
+
+    /// Refuses to inline files larger than `max_bytes`.
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Returns the inlined content once the resource has been generated.
+    pub fn content(&self) -> Option<&str> {
+        self.content.as_deref()
+    }
+}
+
+impl PartialEq for Inline {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+            && self.encoding == other.encoding
+            && self.mime_type == other.mime_type
+            && self.max_bytes == other.max_bytes
+    }
+}
+
+impl Build for Inline {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Virtual())
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = vec![];
+        if let Some(node) = builder.producer(&self.source) {
+            builder.label(&node, "source");
+            dependencies.push(node);
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let bytes = std::fs::read(&self.source)
+            .map_err(|e| format!("failed to read {:?}: {}", self.source, e))?;
+        if let Some(max_bytes) = self.max_bytes {
+            if bytes.len() as u64 > max_bytes {
+                let message = format!(
+                    "{:?} is {} bytes, larger than the inline limit of {} bytes",
+                    self.source,
+                    bytes.len(),
+                    max_bytes
+                );
+                return Err(message.into());
+            }
+        }
+        let content = match self.encoding {
+            Encoding::DataUri => {
+                let mime_type = match &self.mime_type {
+                    Some(mime_type) => mime_type.as_str(),
+                    None => guess_mime_type(&self.source),
+                };
+                let encoded = base64::engine::general_purpose::STANDARD.encode(&bytes);
+                format!("data:{};base64,{}", mime_type, encoded)
+            }
+            Encoding::Text => String::from_utf8(bytes)
+                .map_err(|e| format!("{:?} is not valid UTF-8: {}", self.source, e))?,
+        };
+        self.content = Some(content);
+        Ok(())
+    }
+}
+
+impl Variable for Inline {
+    fn value(&self) -> Option<String> {
+        self.content.clone()
+    }
+}
+
+pub(crate) fn guess_mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        Some("ico") => "image/x-icon",
+        Some("css") => "text/css",
+        Some("js") => "text/javascript",
+        Some("html") => "text/html",
+        Some("txt") => "text/plain",
+        Some("json") => "application/json",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{scratch_dir, RenderTemplate, StringFile};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_inline_standalone() {
+        let dir = scratch_dir("inline-standalone");
+        std::fs::write(dir.join("logo.svg"), "<svg/>").unwrap();
+
+        // an inline without a producer or dependents is still generated
+        let mut builder = Builder::new();
+        let inline = Rc::new(RefCell::new(Inline::data_uri(dir.join("logo.svg"))));
+        builder.require_ref(inline.clone()).unwrap();
+        builder.generate().unwrap();
+        assert_eq!(
+            inline.borrow().content(),
+            Some("data:image/svg+xml;base64,PHN2Zy8+")
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_inline_template_variable() {
+        let dir = scratch_dir("inline-template");
+        std::fs::write(dir.join("page.html"), "<style>{{ css }}</style>").unwrap();
+
+        let mut builder = Builder::new();
+        builder
+            .require(StringFile::new(dir.join("critical.css"), "p{margin:0}"))
+            .unwrap();
+        let inline = Rc::new(RefCell::new(Inline::text(dir.join("critical.css"))));
+        let render = RenderTemplate::new(
+            dir.join("page.html"),
+            dir.join("index.html"),
+            HashMap::new(),
+        )
+        .variable("css", inline);
+        builder.require(render).unwrap();
+        builder.generate().unwrap();
+
+        let html = std::fs::read_to_string(dir.join("index.html")).unwrap();
+        assert_eq!(html, "<style>p{margin:0}</style>");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
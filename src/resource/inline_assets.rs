@@ -0,0 +1,212 @@
+use base64::Engine;
+use regex::{Captures, Regex};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{normalize, Build, Builder, Node, Registration},
+    resource::{inline::guess_mime_type, Directory},
+};
+
+/// Writes a copy of an HTML page with small images and stylesheets inlined
+/// as `data:` URIs.
+///
+/// The `src` of `<img>` tags and the `href` of `<link rel="stylesheet">`
+/// tags are rewritten when they name a local file no larger than
+/// `max_inline_bytes`, 4 KiB by default. Relative references are resolved
+/// against the directory of `path` and absolute ones against `root`.
+///
+/// Assets registered under `root` when the dependencies are resolved are
+/// depended upon, so it must be required after them. Requiring an asset
+/// afterwards fails, as it could be read before it is generated.
+#[derive(Debug)]
+pub struct InlineAssets {
+    id: Option<u64>,
+    source: PathBuf,
+    path: PathBuf,
+    root: PathBuf,
+    max_inline_bytes: u64,
+}
+
+impl InlineAssets {
+    pub fn new<P: AsRef<Path>>(source: P, path: P, root: P) -> Self {
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
+            root: normalize(root),
+            max_inline_bytes: 4096,
+        }
+    }
+
+    /// Inlines only files of at most `max_inline_bytes`.
+    pub fn max_inline_bytes(mut self, max_inline_bytes: u64) -> Self {
+        self.max_inline_bytes = max_inline_bytes;
+        self
+    }
+
+    // returns the data URI for a reference, if it names a small local file
+    fn data_uri(&self, reference: &str) -> Option<String> {
+        if reference.contains(':') || reference.starts_with("//") {
+            return None;
+        }
+        let reference = reference.split(['?', '#']).next().unwrap_or_default();
+        let file = match reference.strip_prefix('/') {
+            Some(absolute) => self.root.join(absolute),
+            None => self.path.parent().unwrap_or(Path::new("")).join(reference),
+        };
+        let metadata = std::fs::metadata(&file).ok()?;
+        if !metadata.is_file() || metadata.len() > self.max_inline_bytes {
+            return None;
+        }
+        let bytes = std::fs::read(&file).ok()?;
+        let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+        Some(format!(
+            "data:{};base64,{}",
+            guess_mime_type(&file),
+            encoded
+        ))
+    }
+}
+
+impl PartialEq for InlineAssets {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+            && self.root == other.root
+            && self.max_inline_bytes == other.max_inline_bytes
+    }
+}
+
+impl Build for InlineAssets {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        if let Some(node) = builder.producer(&self.source) {
+            builder.label(&node, "source");
+            dependencies.push(node);
+        }
+
+        let assets = builder.outputs_under(&self.root, |path| {
+            let mime_type = guess_mime_type(path);
+            mime_type.starts_with("image/") || mime_type == "text/css"
+        });
+        for asset in &assets {
+            if let Some(node) = builder.producer(asset) {
+                builder.label(&node, "asset");
+                dependencies.push(node);
+            }
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let html = std::fs::read_to_string(&self.source)
+            .map_err(|e| format!("failed to read {:?}: {}", self.source, e))?;
+
+        let tag = Regex::new(r"(?i)<(img|link)\b[^>]*>").unwrap();
+        let stylesheet = Regex::new(r#"(?i)\brel\s*=\s*["']?stylesheet\b"#).unwrap();
+        let src = Regex::new(r#"(?i)(\bsrc\s*=\s*)(?:"([^"]*)"|'([^']*)')"#).unwrap();
+        let href = Regex::new(r#"(?i)(\bhref\s*=\s*)(?:"([^"]*)"|'([^']*)')"#).unwrap();
+
+        let html = tag.replace_all(&html, |tag: &Captures| {
+            let attribute = match tag[1].to_ascii_lowercase().as_str() {
+                "img" => &src,
+                _ if stylesheet.is_match(&tag[0]) => &href,
+                _ => return tag[0].to_string(),
+            };
+            attribute
+                .replace(&tag[0], |attribute: &Captures| {
+                    let reference = attribute.get(2).or(attribute.get(3)).unwrap().as_str();
+                    match self.data_uri(reference) {
+                        Some(uri) => format!("{}\"{}\"", &attribute[1], uri),
+                        None => attribute[0].to_string(),
+                    }
+                })
+                .into_owned()
+        });
+        std::fs::write(&self.path, html.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{scratch_dir, StringFile};
+
+    #[test]
+    fn test_inline_assets() {
+        let dir = scratch_dir("inline-assets");
+        let html = concat!(
+            "<link rel=\"stylesheet\" href=\"/style.css\">",
+            "<link rel=\"icon\" href=\"/style.css\">",
+            "<img src=\"big.png\">",
+        );
+        std::fs::write(dir.join("big.png"), vec![0; 64]).unwrap();
+        let mut builder = Builder::new();
+        builder
+            .require(StringFile::new(dir.join("style.css"), "p{}"))
+            .unwrap();
+        builder
+            .require(StringFile::new(dir.join("source.html"), html))
+            .unwrap();
+        let inline =
+            InlineAssets::new(dir.join("source.html"), dir.join("index.html"), dir.clone())
+                .max_inline_bytes(16);
+        builder.require(inline).unwrap();
+        builder.generate().unwrap();
+
+        // only small stylesheets and images are inlined
+        let page = std::fs::read_to_string(dir.join("index.html")).unwrap();
+        assert!(page.contains("href=\"data:text/css;base64,cHt9\""));
+        assert!(page.contains("<link rel=\"icon\" href=\"/style.css\">"));
+        assert!(page.contains("<img src=\"big.png\">"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_inline_assets_asset_required_later() {
+        let dir = scratch_dir("inline-assets-later");
+        let mut builder = Builder::new();
+        let inline =
+            InlineAssets::new(dir.join("source.html"), dir.join("index.html"), dir.clone());
+        builder.require(inline).unwrap();
+
+        // the asset could otherwise be read before it is written
+        let error = builder
+            .require(StringFile::new(dir.join("style.css"), "p{}"))
+            .unwrap_err();
+        assert!(error.to_string().contains("style.css"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,151 @@
+use base64::Engine;
+use sha2::Digest;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::Variable,
+};
+
+/// Hash algorithms allowed in Subresource Integrity metadata.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SriAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+/// Computes the Subresource Integrity value of the file at `path`, e.g.
+/// `sha384-<base64 digest>`, suitable for an `integrity` attribute.
+pub fn sri<P: AsRef<Path>>(
+    path: P,
+    algorithm: SriAlgorithm,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path).map_err(|e| format!("failed to read {:?}: {}", path, e))?;
+    let (prefix, digest) = match algorithm {
+        SriAlgorithm::Sha256 => ("sha256", sha2::Sha256::digest(&bytes).to_vec()),
+        SriAlgorithm::Sha384 => ("sha384", sha2::Sha384::digest(&bytes).to_vec()),
+        SriAlgorithm::Sha512 => ("sha512", sha2::Sha512::digest(&bytes).to_vec()),
+    };
+    let encoded = base64::engine::general_purpose::STANDARD.encode(digest);
+    Ok(format!("{}-{}", prefix, encoded))
+}
+
+/// Computes the integrity value of an asset during generation.
+///
+/// Depends on the resource producing the asset, if one is registered, so
+/// the value always reflects the generated content. Resources which embed
+/// the value should hold a shared reference and depend on this resource,
+/// e.g. `RenderTemplate::variable`.
+#[derive(Debug)]
+pub struct Integrity {
+    id: Option<u64>,
+    source: PathBuf,
+    algorithm: SriAlgorithm,
+    value: Option<String>,
+}
+
+impl Integrity {
+    pub fn new<P: AsRef<Path>>(source: P) -> Self {
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            algorithm: SriAlgorithm::Sha384,
+            value: None,
+        }
+    }
+
+    pub fn algorithm(mut self, algorithm: SriAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Returns the integrity value once the resource has been generated.
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+}
+
+impl PartialEq for Integrity {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source && self.algorithm == other.algorithm
+    }
+}
+
+impl Build for Integrity {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Virtual())
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = vec![];
+        if let Some(node) = builder.producer(&self.source) {
+            builder.label(&node, "source");
+            dependencies.push(node);
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.value = Some(sri(&self.source, self.algorithm)?);
+        Ok(())
+    }
+}
+
+impl Variable for Integrity {
+    fn value(&self) -> Option<String> {
+        self.value.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::scratch_dir;
+
+    #[test]
+    fn test_sri() {
+        let dir = scratch_dir("sri");
+        let path = dir.join("hello.js");
+        std::fs::write(&path, "alert('Hello, world.');").unwrap();
+
+        // the example from the Subresource Integrity documentation
+        assert_eq!(
+            sri(&path, SriAlgorithm::Sha384).unwrap(),
+            "sha384-H8BRh8j48O9oYatfu5AZzq6A9RINhZO5H16dQZngK7T62em8MUt1FLm52t+eX6xO"
+        );
+        assert_eq!(
+            sri(&path, SriAlgorithm::Sha256).unwrap(),
+            "sha256-qznLcsROx4GACP2dm0UCKCzCG+HiZ1guq6ZZDob/Tng="
+        );
+        assert_eq!(
+            sri(&path, SriAlgorithm::Sha512).unwrap(),
+            "sha512-Q2bFTOhEALkN8hOms2FKTDLy7eugP2zFZ1T8LCvX42Fp3WoNr3bjZSAHeOsHrbV1Fu9/A0EzCinRE7Af1ofPrw=="
+        );
+
+        // a missing file names the path
+        let error = sri(dir.join("missing.js"), SriAlgorithm::Sha384).unwrap_err();
+        assert!(error.to_string().contains("missing.js"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
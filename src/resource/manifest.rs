@@ -0,0 +1,156 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::{Directory, Fingerprint},
+};
+
+/// Writes a JSON object mapping the logical paths of fingerprinted assets
+/// to the paths they were written to, e.g.
+/// `{ "css/style.css": "css/style.1a2b3c4d.css" }`.
+///
+/// Paths are relative to the directory containing the manifest where
+/// possible, so that a server can resolve asset names at runtime.
+#[derive(Debug)]
+pub struct Manifest {
+    id: Option<u64>,
+    path: PathBuf,
+    assets: Vec<Rc<RefCell<Fingerprint>>>,
+}
+
+impl Manifest {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            id: None,
+            path: path.as_ref().to_path_buf(),
+            assets: vec![],
+        }
+    }
+
+    /// Adds a fingerprinted asset to the manifest.
+    pub fn asset(mut self, asset: Rc<RefCell<Fingerprint>>) -> Self {
+        self.assets.push(asset);
+        self
+    }
+
+    fn relative(&self, path: &Path) -> String {
+        let relative = match self.path.parent() {
+            Some(parent) => path.strip_prefix(parent).unwrap_or(path),
+            None => path,
+        };
+        let segments: Vec<String> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        segments.join("/")
+    }
+}
+
+impl PartialEq for Manifest {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+            && self.assets.len() == other.assets.len()
+            && self
+                .assets
+                .iter()
+                .zip(&other.assets)
+                .all(|(asset, other)| *asset.borrow() == *other.borrow())
+    }
+}
+
+impl Build for Manifest {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        for asset in self.assets.clone() {
+            let node = builder.require_ref(asset)?;
+            builder.label(&node, "asset");
+            dependencies.push(node);
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut entries = serde_json::Map::new();
+        for asset in &self.assets {
+            let asset = asset.borrow();
+            let fingerprinted = match asset.fingerprinted() {
+                Some(path) => path,
+                None => return Err(format!("{:?} was not generated", asset).into()),
+            };
+            entries.insert(
+                self.relative(asset.logical()),
+                serde_json::Value::String(self.relative(fingerprinted)),
+            );
+        }
+
+        let json = serde_json::to_string_pretty(&entries)?;
+        std::fs::write(&self.path, json)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::OutputCollision;
+
+    fn asset(name: &str) -> Rc<RefCell<Fingerprint>> {
+        let asset = Fingerprint::new(format!("assets/{}", name), format!("site/{}", name));
+        Rc::new(RefCell::new(asset))
+    }
+
+    #[test]
+    fn test_manifest_equal_assets() {
+        let mut builder = Builder::new();
+        let node = builder
+            .require(Manifest::new("site/manifest.json").asset(asset("style.css")))
+            .unwrap();
+
+        // the same definition resolves to the existing node
+        let result = builder.require(Manifest::new("site/manifest.json").asset(asset("style.css")));
+        assert_eq!(result.unwrap().id, node.id);
+    }
+
+    #[test]
+    fn test_manifest_different_assets_collide() {
+        let mut builder = Builder::new();
+        builder
+            .require(Manifest::new("site/manifest.json").asset(asset("style.css")))
+            .unwrap();
+
+        // a manifest of other assets at the same path is not a duplicate
+        let result = builder.require(Manifest::new("site/manifest.json").asset(asset("app.js")));
+        let error = result.unwrap_err();
+        assert!(error.downcast_ref::<OutputCollision>().is_some());
+    }
+}
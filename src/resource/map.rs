@@ -0,0 +1,264 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::filter::{build_filter, globs, regexes, relative_files, Filter, Pattern, Walk},
+};
+
+type Map<T> = Box<dyn Fn(&Path, &Path) -> T>;
+
+/// Creates one resource per file under `source`.
+///
+/// Each matched file is passed to a mapping closure along with its path
+/// relative to `source`, e.g. to render every markdown file in a directory
+/// to a page of the same name. This generalizes `CopyDir` to arbitrary
+/// per-file resources.
+pub struct MapDir<T: Build + 'static> {
+    id: Option<u64>,
+    source: PathBuf,
+    resources: Vec<Rc<RefCell<T>>>,
+}
+
+impl<T: Build + 'static> std::fmt::Debug for MapDir<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MapDir")
+            .field("source", &self.source)
+            .field("resources", &self.resources.len())
+            .finish()
+    }
+}
+
+impl<T: Build + 'static> MapDir<T> {
+    pub fn new<P: AsRef<Path>>(source: P, filter: Filter, map: Map<T>) -> Self {
+        // symlinks are not followed, so the walk cannot fail
+        let resources = relative_files(&source, &Walk::default())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(filter.as_ref())
+            .map(|relative| map(&source.as_ref().join(&relative), Path::new(&relative)))
+            .map(|resource| Rc::new(RefCell::new(resource)))
+            .collect();
+
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            resources,
+        }
+    }
+
+    pub fn builder<P, F>(source: P, map: F) -> MapDirBuilder<T>
+    where
+        P: AsRef<Path>,
+        F: Fn(&Path, &Path) -> T + 'static,
+    {
+        MapDirBuilder::new(source, map)
+    }
+
+    /// Returns the resources created for each matched file.
+    pub fn resources(&self) -> Vec<Rc<RefCell<T>>> {
+        self.resources.clone()
+    }
+}
+
+impl<T: Build + 'static> PartialEq for MapDir<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl<T: Build + 'static> Build for MapDir<T> {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Virtual())
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = vec![];
+        for resource in self.resources.clone() {
+            let node = builder.require_ref(resource)?;
+            builder.label(&node, "file");
+            dependencies.push(node);
+        }
+        Ok(dependencies)
+    }
+}
+
+pub struct MapDirBuilder<T: Build + 'static> {
+    source: PathBuf,
+    map: Map<T>,
+
+    include: Option<Vec<Pattern>>,
+    exclude: Option<Vec<Pattern>>,
+}
+
+impl<T: Build + 'static> MapDirBuilder<T> {
+    pub fn new<P, F>(source: P, map: F) -> Self
+    where
+        P: AsRef<Path>,
+        F: Fn(&Path, &Path) -> T + 'static,
+    {
+        Self {
+            source: source.as_ref().to_path_buf(),
+            map: Box::new(map),
+            include: None,
+            exclude: None,
+        }
+    }
+
+    pub fn include(mut self, patterns: Vec<&str>) -> Self {
+        self.include = Some(regexes(patterns));
+        self
+    }
+
+    pub fn exclude(mut self, patterns: Vec<&str>) -> Self {
+        self.exclude = Some(regexes(patterns));
+        self
+    }
+
+    /// Like `include`, but adds to the include patterns given before.
+    pub fn add_include(mut self, patterns: Vec<&str>) -> Self {
+        self.include
+            .get_or_insert_with(Vec::new)
+            .extend(regexes(patterns));
+        self
+    }
+
+    /// Like `exclude`, but adds to the exclude patterns given before.
+    pub fn add_exclude(mut self, patterns: Vec<&str>) -> Self {
+        self.exclude
+            .get_or_insert_with(Vec::new)
+            .extend(regexes(patterns));
+        self
+    }
+
+    /// Like `include`, but with shell-style globs such as `*.md`.
+    pub fn include_globs(mut self, patterns: Vec<&str>) -> Self {
+        self.include = Some(globs(patterns));
+        self
+    }
+
+    /// Like `exclude`, but with shell-style globs.
+    pub fn exclude_globs(mut self, patterns: Vec<&str>) -> Self {
+        self.exclude = Some(globs(patterns));
+        self
+    }
+
+    /// Like `include_globs`, but adds to the include patterns given before.
+    pub fn add_include_globs(mut self, patterns: Vec<&str>) -> Self {
+        self.include
+            .get_or_insert_with(Vec::new)
+            .extend(globs(patterns));
+        self
+    }
+
+    /// Like `exclude_globs`, but adds to the exclude patterns given before.
+    pub fn add_exclude_globs(mut self, patterns: Vec<&str>) -> Self {
+        self.exclude
+            .get_or_insert_with(Vec::new)
+            .extend(globs(patterns));
+        self
+    }
+
+    pub fn build(self) -> MapDir<T> {
+        let filter = build_filter(self.include, self.exclude);
+        MapDir::new(self.source, filter, self.map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{scratch_dir, StringFile};
+
+    fn pages(source: &Path, output: &Path) -> MapDirBuilder<StringFile> {
+        let output = output.to_path_buf();
+        MapDir::builder(source, move |source, relative| {
+            let path = output.join(relative).with_extension("html");
+            StringFile::new(path, &source.to_string_lossy())
+        })
+    }
+
+    #[test]
+    fn test_map_dir() {
+        let source = scratch_dir("map");
+        let output = scratch_dir("map-out");
+        std::fs::create_dir_all(source.join("posts")).unwrap();
+        std::fs::write(source.join("index.md"), "").unwrap();
+        std::fs::write(source.join("posts/first.md"), "").unwrap();
+        std::fs::write(source.join("posts/draft.md"), "").unwrap();
+        std::fs::write(source.join("notes.txt"), "").unwrap();
+
+        let map_dir = pages(&source, &output).include(vec![r"\.md$"]).build();
+        assert_eq!(map_dir.resources().len(), 3);
+        let mut builder = Builder::new();
+        builder.require(map_dir).unwrap();
+        builder.generate().unwrap();
+
+        // each matched file is passed with its path relative to the source
+        let index = std::fs::read_to_string(output.join("index.html")).unwrap();
+        assert_eq!(index, source.join("index.md").to_string_lossy());
+        let first = std::fs::read_to_string(output.join("posts/first.html")).unwrap();
+        assert_eq!(first, source.join("posts/first.md").to_string_lossy());
+        assert!(!output.join("notes.html").exists());
+
+        // include replaces earlier patterns, and add_include extends them
+        let map_dir = pages(&source, &output)
+            .include(vec!["notes"])
+            .include(vec![r"\.md$"])
+            .build();
+        assert_eq!(map_dir.resources().len(), 3);
+        let map_dir = pages(&source, &output)
+            .include(vec!["notes"])
+            .add_include(vec![r"\.md$"])
+            .build();
+        assert_eq!(map_dir.resources().len(), 4);
+
+        // globs combine with regexes
+        let map_dir = pages(&source, &output)
+            .include_globs(vec!["posts/*.md"])
+            .add_include(vec!["^notes"])
+            .build();
+        assert_eq!(map_dir.resources().len(), 3);
+        let map_dir = pages(&source, &output).exclude_globs(vec!["*.md"]).build();
+        assert_eq!(map_dir.resources().len(), 1);
+
+        // excluded files are not mapped
+        let map_dir = pages(&source, &output).exclude(vec!["draft"]).build();
+        let resources = map_dir.resources();
+        let sources: Vec<String> = resources
+            .iter()
+            .map(|resource| format!("{:?}", resource.borrow()))
+            .collect();
+        assert_eq!(sources.len(), 3);
+        assert!(sources.iter().all(|source| !source.contains("draft")));
+
+        std::fs::remove_dir_all(&source).unwrap();
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn test_map_dir_missing_source() {
+        // a missing source maps no files
+        let source = std::env::temp_dir().join("blanket-rs-map-missing");
+        let map_dir = pages(&source, Path::new("out")).build();
+        assert!(map_dir.resources().is_empty());
+    }
+}
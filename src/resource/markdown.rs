@@ -0,0 +1,361 @@
+use encoding_rs::Encoding;
+#[cfg(feature = "highlight")]
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Tag, TagEnd};
+use pulldown_cmark::{Options, Parser};
+use regex::Regex;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::OnceLock;
+#[cfg(feature = "highlight")]
+use syntect::{highlighting::ThemeSet, html::highlighted_html_for_string, parsing::SyntaxSet};
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::{copy::read_text, Directory},
+};
+
+/// Renders a markdown file to HTML.
+///
+/// Front matter between `---` fences at the top of the file is removed from
+/// the output. Its `key: value` pairs are available from `front_matter` once
+/// the resource has been generated. Nested YAML structures are not parsed.
+///
+/// With a highlighting theme, which requires the `highlight` feature,
+/// fenced code blocks in a language known to `syntect` are highlighted with
+/// inline styles. Other code blocks are rendered as plain `<pre><code>`.
+#[derive(Debug)]
+pub struct RenderMarkdown {
+    id: Option<u64>,
+    source: PathBuf,
+    path: PathBuf,
+    template: Option<String>,
+    theme: Option<String>,
+    front_matter: HashMap<String, String>,
+    encoding: Option<&'static Encoding>,
+}
+
+impl RenderMarkdown {
+    pub fn new<P: AsRef<Path>>(source: P, path: P) -> Self {
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
+            template: None,
+            theme: None,
+            front_matter: HashMap::new(),
+            encoding: None,
+        }
+    }
+
+    /// Decodes the source from `encoding` rather than UTF-8. The output is
+    /// written as UTF-8, and malformed input is reported as an error.
+    pub fn with_encoding(mut self, encoding: &'static Encoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// Wraps the rendered HTML in `template`, replacing the
+    /// `{{ content }}` placeholder.
+    pub fn template(mut self, template: &str) -> Self {
+        self.template = Some(template.to_string());
+        self
+    }
+
+    /// Highlights fenced code blocks with the named theme from `syntect`'s
+    /// defaults, e.g. `base16-ocean.dark` or `InspiredGitHub`.
+    #[cfg(feature = "highlight")]
+    pub fn highlight(mut self, theme: &str) -> Self {
+        self.theme = Some(theme.to_string());
+        self
+    }
+
+    /// Returns the front matter of the source once it has been generated.
+    pub fn front_matter(&self) -> &HashMap<String, String> {
+        &self.front_matter
+    }
+}
+
+impl PartialEq for RenderMarkdown {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+            && self.template == other.template
+            && self.theme == other.theme
+            && self.encoding == other.encoding
+    }
+}
+
+impl Build for RenderMarkdown {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        if let Some(node) = builder.producer(&self.source) {
+            builder.label(&node, "source");
+            dependencies.push(node);
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let text = read_text(&self.source, self.encoding)?;
+        let (front_matter, body) = split_front_matter(&text);
+        self.front_matter = front_matter;
+
+        let mut options = Options::empty();
+        options.insert(Options::ENABLE_TABLES);
+        options.insert(Options::ENABLE_FOOTNOTES);
+        options.insert(Options::ENABLE_STRIKETHROUGH);
+        options.insert(Options::ENABLE_TASKLISTS);
+        let parser = Parser::new_ext(body, options);
+        let mut html = String::new();
+        match &self.theme {
+            #[cfg(feature = "highlight")]
+            Some(theme) => {
+                let events = highlight(parser, theme)
+                    .map_err(|e| format!("failed to render {:?}: {}", self.source, e))?;
+                pulldown_cmark::html::push_html(&mut html, events.into_iter());
+            }
+            _ => pulldown_cmark::html::push_html(&mut html, parser),
+        }
+
+        let html = match &self.template {
+            Some(template) => {
+                static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+                let placeholder =
+                    PLACEHOLDER.get_or_init(|| Regex::new(r"\{\{\s*content\s*\}\}").unwrap());
+                placeholder
+                    .replace_all(template, regex::NoExpand(&html))
+                    .into_owned()
+            }
+            None => html,
+        };
+        std::fs::write(&self.path, html)?;
+        Ok(())
+    }
+}
+
+// replaces fenced code blocks in a known language with highlighted html
+#[cfg(feature = "highlight")]
+fn highlight<'a>(parser: Parser<'a>, theme: &str) -> Result<Vec<Event<'a>>, String> {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    let syntaxes = SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines);
+    let theme = THEMES
+        .get_or_init(ThemeSet::load_defaults)
+        .themes
+        .get(theme)
+        .ok_or(format!("unknown highlighting theme {:?}", theme))?;
+
+    let mut events = vec![];
+    let mut block: Option<(String, Vec<Event>)> = None;
+    for event in parser {
+        match (event, block.as_mut()) {
+            (Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))), None) => {
+                let language = info.split([' ', ',']).next().unwrap_or_default();
+                let start = Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info.clone())));
+                block = Some((language.to_string(), vec![start]));
+            }
+            (Event::End(TagEnd::CodeBlock), Some((language, buffered))) => {
+                let code: String = buffered
+                    .iter()
+                    .filter_map(|event| match event {
+                        Event::Text(text) => Some(text.as_ref()),
+                        _ => None,
+                    })
+                    .collect();
+                match syntaxes.find_syntax_by_token(language) {
+                    Some(syntax) => {
+                        let highlighted =
+                            highlighted_html_for_string(&code, syntaxes, syntax, theme)
+                                .map_err(|e| e.to_string())?;
+                        events.push(Event::Html(CowStr::from(highlighted)));
+                    }
+                    None => {
+                        events.append(buffered);
+                        events.push(Event::End(TagEnd::CodeBlock));
+                    }
+                }
+                block = None;
+            }
+            (event, Some((_, buffered))) => buffered.push(event),
+            (event, None) => events.push(event),
+        }
+    }
+    Ok(events)
+}
+
+// separates front matter fenced by `---` lines from the markdown body
+fn split_front_matter(text: &str) -> (HashMap<String, String>, &str) {
+    let mut front_matter = HashMap::new();
+    let rest = match text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    {
+        Some(rest) => rest,
+        None => return (front_matter, text),
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end();
+        if line == "---" {
+            return (front_matter, &rest[offset..]);
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim().trim_matches('"').trim_matches('\'');
+            front_matter.insert(key.trim().to_string(), value.to_string());
+        }
+    }
+
+    // an unterminated fence is not front matter
+    (HashMap::new(), text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::scratch_dir;
+
+    #[test]
+    fn test_split_front_matter() {
+        let text = "---\ntitle: \"Hello: world\"\nauthor: 'ada'\ndraft:\n---\n# Hello\n";
+        let (front_matter, body) = split_front_matter(text);
+        assert_eq!(front_matter["title"], "Hello: world");
+        assert_eq!(front_matter["author"], "ada");
+        assert_eq!(front_matter["draft"], "");
+        assert_eq!(body, "# Hello\n");
+
+        // windows line endings
+        let (front_matter, body) = split_front_matter("---\r\ntitle: Hi\r\n---\r\nbody");
+        assert_eq!(front_matter["title"], "Hi");
+        assert_eq!(body, "body");
+    }
+
+    #[test]
+    fn test_split_front_matter_absent() {
+        // without an opening fence the text is all body
+        let text = "# Hello\n---\ntitle: no\n";
+        let (front_matter, body) = split_front_matter(text);
+        assert!(front_matter.is_empty());
+        assert_eq!(body, text);
+
+        // an unterminated fence is not front matter
+        let text = "---\ntitle: no\n# Hello\n";
+        let (front_matter, body) = split_front_matter(text);
+        assert!(front_matter.is_empty());
+        assert_eq!(body, text);
+    }
+
+    #[test]
+    fn test_render_markdown_front_matter() {
+        let dir = scratch_dir("markdown-front-matter");
+        std::fs::write(dir.join("post.md"), "---\ntitle: Hello\n---\n# Hello\n").unwrap();
+
+        let mut markdown = RenderMarkdown::new(dir.join("post.md"), dir.join("post.html"))
+            .template("<main>{{ content }}</main>");
+        markdown.generate().unwrap();
+
+        let html = std::fs::read_to_string(dir.join("post.html")).unwrap();
+        assert_eq!(html, "<main><h1>Hello</h1>\n</main>");
+        assert_eq!(markdown.front_matter()["title"], "Hello");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_render_markdown_encoding() {
+        let dir = scratch_dir("markdown-encoding");
+        std::fs::write(
+            dir.join("post.md"),
+            b"# \x82\xb1\x82\xf1\x82\xc9\x82\xbf\x82\xcd\n",
+        )
+        .unwrap();
+
+        let mut markdown = RenderMarkdown::new(dir.join("post.md"), dir.join("post.html"))
+            .with_encoding(encoding_rs::SHIFT_JIS);
+        markdown.generate().unwrap();
+        let html = std::fs::read_to_string(dir.join("post.html")).unwrap();
+        assert_eq!(html, "<h1>こんにちは</h1>\n");
+
+        // malformed input names the file and offset
+        std::fs::write(dir.join("post.md"), b"# \x82").unwrap();
+        let error = markdown.generate().unwrap_err().to_string();
+        assert!(error.contains("post.md"));
+        assert!(error.contains("byte offset 2"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "highlight")]
+    fn highlighted(markdown: &str, theme: &str) -> Result<String, String> {
+        let mut html = String::new();
+        let events = highlight(Parser::new(markdown), theme)?;
+        pulldown_cmark::html::push_html(&mut html, events.into_iter());
+        Ok(html)
+    }
+
+    #[test]
+    #[cfg(feature = "highlight")]
+    fn test_highlight_known_language() {
+        let html = highlighted("```rust\nfn main() {}\n```\n", "InspiredGitHub").unwrap();
+        assert!(html.starts_with("<pre style="));
+        assert!(html.contains("<span style="));
+        assert!(!html.contains("<code"));
+    }
+
+    #[test]
+    #[cfg(feature = "highlight")]
+    fn test_highlight_unknown_language() {
+        // unknown languages and indented blocks are rendered plainly
+        let html = highlighted(
+            "```nosuchlang
+a < b
+```
+",
+            "InspiredGitHub",
+        )
+        .unwrap();
+        assert_eq!(
+            html,
+            "<pre><code class=\"language-nosuchlang\">a &lt; b\n</code></pre>\n"
+        );
+        let html = highlighted("    plain\n", "InspiredGitHub").unwrap();
+        assert_eq!(html, "<pre><code>plain\n</code></pre>\n");
+    }
+
+    #[test]
+    #[cfg(feature = "highlight")]
+    fn test_highlight_unknown_theme() {
+        let error = highlighted("text", "no-such-theme").unwrap_err();
+        assert_eq!(error, "unknown highlighting theme \"no-such-theme\"");
+    }
+}
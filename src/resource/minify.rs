@@ -0,0 +1,98 @@
+use lightningcss::stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::Directory,
+};
+
+/// Writes a minified copy of a CSS file.
+///
+/// A stylesheet which fails to parse is an error naming the source, and
+/// nothing is written.
+#[derive(Debug)]
+pub struct MinifyCss {
+    id: Option<u64>,
+    source: PathBuf,
+    path: PathBuf,
+}
+
+impl MinifyCss {
+    pub fn new<P: AsRef<Path>>(source: P, path: P) -> Self {
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl PartialEq for MinifyCss {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Build for MinifyCss {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        if let Some(node) = builder.producer(&self.source) {
+            builder.label(&node, "source");
+            dependencies.push(node);
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(&self.source)
+            .map_err(|e| format!("failed to read {:?}: {}", self.source, e))?;
+        let css =
+            minify(&text).map_err(|e| format!("failed to minify {:?}: {}", self.source, e))?;
+        std::fs::write(&self.path, css)?;
+        Ok(())
+    }
+}
+
+fn minify(text: &str) -> Result<String, String> {
+    let mut stylesheet =
+        StyleSheet::parse(text, ParserOptions::default()).map_err(|e| e.to_string())?;
+    stylesheet
+        .minify(MinifyOptions::default())
+        .map_err(|e| e.to_string())?;
+    let printer = PrinterOptions {
+        minify: true,
+        ..PrinterOptions::default()
+    };
+    let css = stylesheet.to_css(printer).map_err(|e| e.to_string())?;
+    Ok(css.code)
+}
@@ -1,5 +1,86 @@
+mod brotli;
+mod concat;
+mod convert;
 mod copy;
 mod directory;
+#[cfg(feature = "image")]
+mod favicons;
+mod feed;
+#[cfg(feature = "fetch")]
+mod fetch;
+mod filter;
+mod fingerprint;
+mod gzip;
+mod inline;
+mod inline_assets;
+mod integrity;
+mod manifest;
+mod map;
+mod markdown;
+#[cfg(feature = "css")]
+mod minify;
+#[cfg(feature = "css")]
+mod purge;
+#[cfg(feature = "image")]
+mod resize;
+mod rewrite;
+mod robots;
+#[cfg(feature = "scss")]
+mod scss;
+mod search;
+mod sitemap;
+#[cfg(feature = "image")]
+mod srcset;
+mod string;
+mod svg;
+mod template;
+mod toc;
+mod xml;
 
-pub use copy::{CopyDir, CopyFile};
+pub use self::brotli::Brotli;
+pub use concat::Concat;
+pub use convert::ConvertData;
+pub use copy::{CopyDir, CopyFile, SymlinkFile};
 pub use directory::Directory;
+#[cfg(feature = "image")]
+pub use favicons::Favicons;
+pub use feed::{Feed, FeedItem};
+#[cfg(feature = "fetch")]
+pub use fetch::Fetch;
+pub use fingerprint::Fingerprint;
+pub use gzip::Gzip;
+pub use inline::Inline;
+pub use inline_assets::InlineAssets;
+pub use integrity::{sri, Integrity, SriAlgorithm};
+pub use manifest::Manifest;
+pub use map::{MapDir, MapDirBuilder};
+pub use markdown::RenderMarkdown;
+#[cfg(feature = "css")]
+pub use minify::MinifyCss;
+#[cfg(feature = "css")]
+pub use purge::PurgeCss;
+#[cfg(feature = "image")]
+pub use resize::ResizeImage;
+pub use rewrite::RewriteRefs;
+pub use robots::{Robots, RobotsRule};
+#[cfg(feature = "scss")]
+pub use scss::CompileScss;
+pub use search::{SearchIndex, SearchIndexBuilder};
+pub use sitemap::Sitemap;
+#[cfg(feature = "image")]
+pub use srcset::Srcset;
+pub use string::StringFile;
+pub use svg::OptimizeSvg;
+pub use template::{RenderTemplate, Variable};
+pub use toc::TableOfContents;
+
+// creates an empty directory under the system temporary directory for a test
+#[cfg(test)]
+pub(crate) fn scratch_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("blanket-rs-{}-{}", name, std::process::id()));
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
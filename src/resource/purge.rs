@@ -0,0 +1,321 @@
+use lightningcss::{
+    rules::{CssRule, CssRuleList},
+    stylesheet::{ParserOptions, PrinterOptions, StyleSheet},
+    traits::ToCss,
+};
+use regex::Regex;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::Directory,
+};
+
+/// Writes a copy of a stylesheet without the selectors unused by a set of
+/// HTML pages.
+///
+/// A selector is kept when every class, id and element name it contains
+/// appears in one of the pages or in the safelist. Rules left without
+/// selectors are removed, along with `@media` and `@supports` blocks left
+/// empty. Other at-rules are kept. Arguments of functional pseudo-classes
+/// such as `:not()` are not checked.
+#[derive(Debug)]
+pub struct PurgeCss {
+    id: Option<u64>,
+    source: PathBuf,
+    path: PathBuf,
+    pages: Vec<PathBuf>,
+    safelist: HashSet<String>,
+}
+
+impl PurgeCss {
+    pub fn new<P: AsRef<Path>>(source: P, path: P, pages: Vec<P>) -> Self {
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
+            pages: pages
+                .iter()
+                .map(|page| page.as_ref().to_path_buf())
+                .collect(),
+            safelist: HashSet::new(),
+        }
+    }
+
+    /// Always keeps selectors using these names of classes, ids or
+    /// elements, given without `.` or `#`, e.g. classes toggled by scripts.
+    pub fn safelist(mut self, names: Vec<&str>) -> Self {
+        self.safelist
+            .extend(names.into_iter().map(|name| name.to_string()));
+        self
+    }
+}
+
+impl PartialEq for PurgeCss {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source && self.pages == other.pages && self.safelist == other.safelist
+    }
+}
+
+impl Build for PurgeCss {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        if let Some(node) = builder.producer(&self.source) {
+            builder.label(&node, "source");
+            dependencies.push(node);
+        }
+        for page in &self.pages {
+            if let Some(node) = builder.producer(page) {
+                builder.label(&node, "page");
+                dependencies.push(node);
+            }
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut used = self.safelist.clone();
+        for page in &self.pages {
+            let html = std::fs::read_to_string(page)
+                .map_err(|e| format!("failed to read {:?}: {}", page, e))?;
+            used.extend(names(&html));
+        }
+
+        let text = std::fs::read_to_string(&self.source)
+            .map_err(|e| format!("failed to read {:?}: {}", self.source, e))?;
+        let purged = || -> Result<String, String> {
+            let mut stylesheet =
+                StyleSheet::parse(&text, ParserOptions::default()).map_err(|e| e.to_string())?;
+            purge(&mut stylesheet.rules, &Used::new(used));
+            let css = stylesheet
+                .to_css(PrinterOptions::default())
+                .map_err(|e| e.to_string())?;
+            Ok(css.code)
+        };
+        let css = purged().map_err(|e| format!("failed to purge {:?}: {}", self.source, e))?;
+        std::fs::write(&self.path, css)?;
+        Ok(())
+    }
+}
+
+// returns the element names, classes and ids used in an html document
+fn names(html: &str) -> HashSet<String> {
+    let tag = Regex::new(r"<([a-zA-Z][\w-]*)").unwrap();
+    let attribute =
+        Regex::new(r#"(?i)\b(?:class|id)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap();
+    let mut names: HashSet<String> = tag
+        .captures_iter(html)
+        .map(|tag| tag[1].to_ascii_lowercase())
+        .collect();
+    for attribute in attribute.captures_iter(html) {
+        let value = attribute
+            .get(1)
+            .or(attribute.get(2))
+            .or(attribute.get(3))
+            .unwrap();
+        names.extend(
+            value
+                .as_str()
+                .split_whitespace()
+                .map(|name| name.to_string()),
+        );
+    }
+    names
+}
+
+// removes unused selectors, and the rules and blocks left empty
+fn purge(rules: &mut CssRuleList, used: &Used) {
+    rules.0.retain_mut(|rule| match rule {
+        CssRule::Style(style) => {
+            style.selectors.0.retain(|selector| {
+                match selector.to_css_string(PrinterOptions::default()) {
+                    Ok(selector) => used.selector(&selector),
+                    Err(_) => true,
+                }
+            });
+            !style.selectors.0.is_empty()
+        }
+        CssRule::Media(media) => {
+            purge(&mut media.rules, used);
+            !media.rules.0.is_empty()
+        }
+        CssRule::Supports(supports) => {
+            purge(&mut supports.rules, used);
+            !supports.rules.0.is_empty()
+        }
+        _ => true,
+    });
+}
+
+// the names used by the pages, and the patterns picking names out of
+// selectors, compiled once per stylesheet
+struct Used {
+    names: HashSet<String>,
+    attribute: Regex,
+    arguments: Regex,
+    pseudo: Regex,
+    named: Regex,
+    element: Regex,
+}
+
+impl Used {
+    fn new(names: HashSet<String>) -> Self {
+        Self {
+            names,
+            attribute: Regex::new(r"\[[^\]]*\]").unwrap(),
+            arguments: Regex::new(r"\([^()]*\)").unwrap(),
+            pseudo: Regex::new(r"(^|[^\\])::?[\w-]+").unwrap(),
+            named: Regex::new(r"[.#]((?:[\w-]|\\.)+)").unwrap(),
+            element: Regex::new(r"(?:^|[\s>+~])([a-zA-Z][\w-]*)").unwrap(),
+        }
+    }
+
+    // checks that every class, id and element name in a selector is used
+    fn selector(&self, selector: &str) -> bool {
+        let mut selector = self.attribute.replace_all(selector, "").into_owned();
+        while self.arguments.is_match(&selector) {
+            selector = self.arguments.replace_all(&selector, "").into_owned();
+        }
+        // escaped colons belong to names, e.g. `.md\:flex`
+        let selector = self.pseudo.replace_all(&selector, "$1");
+
+        let mut names: Vec<String> = self
+            .named
+            .captures_iter(&selector)
+            .map(|name| name[1].replace('\\', ""))
+            .collect();
+        names.extend(
+            self.element
+                .captures_iter(&selector)
+                .map(|element| element[1].to_ascii_lowercase()),
+        );
+        names.iter().all(|name| self.names.contains(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::scratch_dir;
+
+    fn used(names: &[&str]) -> Used {
+        Used::new(names.iter().map(|name| name.to_string()).collect())
+    }
+
+    fn purged(css: &str, names: &[&str]) -> String {
+        let mut stylesheet = StyleSheet::parse(css, ParserOptions::default()).unwrap();
+        purge(&mut stylesheet.rules, &used(names));
+        stylesheet.to_css(PrinterOptions::default()).unwrap().code
+    }
+
+    #[test]
+    fn test_names() {
+        let html = r#"<main id="content"><P class="card  wide"><a class=link>x</a></P></main>"#;
+        let mut names: Vec<String> = names(html).into_iter().collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec!["a", "card", "content", "link", "main", "p", "wide"]
+        );
+    }
+
+    #[test]
+    fn test_selector() {
+        let used = used(&["div", "a", "card", "content", "md:flex"]);
+
+        // every class, id and element must be used
+        assert!(used.selector("div.card"));
+        assert!(used.selector("#content > a"));
+        assert!(!used.selector("div.hero"));
+        assert!(!used.selector("#sidebar"));
+        assert!(!used.selector("span"));
+
+        // pseudo-classes, pseudo-elements and attributes are ignored
+        assert!(used.selector("a:hover"));
+        assert!(used.selector(".card::before"));
+        assert!(used.selector("a[href^=\"http\"]"));
+        assert!(used.selector("div:not(.hidden)"));
+
+        // an escaped colon is part of a class name
+        assert!(used.selector(".md\\:flex"));
+        assert!(!used.selector(".lg\\:flex"));
+    }
+
+    #[test]
+    fn test_purge_rules() {
+        let css = ".card, .hero { color: red } .hero { color: blue }";
+        assert_eq!(purged(css, &["card"]), ".card {\n  color: red;\n}\n");
+    }
+
+    #[test]
+    fn test_purge_at_rules() {
+        let css = "@media (min-width: 40em) { .hero { color: red } } \
+                   @supports (display: grid) { .card { display: grid } } \
+                   @font-face { font-family: Mono; src: url(mono.woff2) }";
+        let purged = purged(css, &["card"]);
+
+        // emptied blocks are removed and other at-rules are kept
+        assert!(!purged.contains("@media"));
+        assert!(purged.contains("@supports (display: grid)"));
+        assert!(purged.contains(".card"));
+        assert!(purged.contains("@font-face"));
+    }
+
+    #[test]
+    fn test_purge_css_generate() {
+        let dir = scratch_dir("purge");
+        std::fs::write(dir.join("index.html"), r#"<p class="intro">hi</p>"#).unwrap();
+        std::fs::write(
+            dir.join("style.css"),
+            "p { margin: 0 } .intro { color: red } .open { display: block } .unused { color: blue }",
+        )
+        .unwrap();
+
+        let mut purge = PurgeCss::new(
+            dir.join("style.css"),
+            dir.join("purged.css"),
+            vec![dir.join("index.html")],
+        )
+        .safelist(vec!["open"]);
+        purge.generate().unwrap();
+
+        let css = std::fs::read_to_string(dir.join("purged.css")).unwrap();
+        assert!(css.contains(".intro"));
+        assert!(css.contains(".open"));
+        assert!(css.contains("p {"));
+        assert!(!css.contains(".unused"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,132 @@
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, ImageFormat};
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::Directory,
+};
+
+/// Writes a resized copy of an image, e.g. a thumbnail.
+///
+/// The image is scaled down to fit within the maximum width and height,
+/// preserving its aspect ratio. Images which already fit are not enlarged.
+/// The output format is chosen from the destination's extension.
+#[derive(Debug)]
+pub struct ResizeImage {
+    id: Option<u64>,
+    source: PathBuf,
+    path: PathBuf,
+    max_width: Option<u32>,
+    max_height: Option<u32>,
+    quality: u8,
+}
+
+impl ResizeImage {
+    pub fn new<P: AsRef<Path>>(source: P, path: P) -> Self {
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
+            max_width: None,
+            max_height: None,
+            quality: 85,
+        }
+    }
+
+    pub fn max_width(mut self, width: u32) -> Self {
+        self.max_width = Some(width);
+        self
+    }
+
+    pub fn max_height(mut self, height: u32) -> Self {
+        self.max_height = Some(height);
+        self
+    }
+
+    /// Sets the JPEG quality from 1 to 100. Defaults to 85. Other formats
+    /// are encoded losslessly.
+    pub fn quality(mut self, quality: u8) -> Self {
+        self.quality = quality;
+        self
+    }
+}
+
+impl PartialEq for ResizeImage {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+            && self.max_width == other.max_width
+            && self.max_height == other.max_height
+            && self.quality == other.quality
+    }
+}
+
+impl Build for ResizeImage {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        if let Some(node) = builder.producer(&self.source) {
+            builder.label(&node, "source");
+            dependencies.push(node);
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let format = ImageFormat::from_path(&self.path)
+            .map_err(|e| format!("unsupported image format for {:?}: {}", self.path, e))?;
+        let image = image::open(&self.source)
+            .map_err(|e| format!("failed to decode {:?}: {}", self.source, e))?;
+
+        let width = self.max_width.unwrap_or(u32::MAX).min(image.width());
+        let height = self.max_height.unwrap_or(u32::MAX).min(image.height());
+        let image = if width < image.width() || height < image.height() {
+            image.resize(width, height, FilterType::Lanczos3)
+        } else {
+            image
+        };
+
+        let file = File::create(&self.path)
+            .map_err(|e| format!("failed to create {:?}: {}", self.path, e))?;
+        let mut writer = BufWriter::new(file);
+        let result = match format {
+            ImageFormat::Jpeg => {
+                let encoder = JpegEncoder::new_with_quality(&mut writer, self.quality);
+                image.to_rgb8().write_with_encoder(encoder)
+            }
+            _ => image.write_to(&mut writer, format),
+        };
+        result.map_err(|e| format!("failed to encode {:?}: {}", self.path, e))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+use regex::{Captures, Regex};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{normalize, Build, Builder, Node, Registration},
+    resource::{Directory, Fingerprint},
+};
+
+/// Writes a copy of an HTML page with references to fingerprinted assets
+/// rewritten to their hashed names, e.g. `href="/css/style.css"` becomes
+/// `href="/css/style.1a2b3c4d.css"`.
+///
+/// The `href` and `src` attributes of every tag are rewritten when they
+/// name the logical path of an asset. Relative references are resolved
+/// against the directory of `path` and absolute ones against `root`.
+#[derive(Debug)]
+pub struct RewriteRefs {
+    id: Option<u64>,
+    source: PathBuf,
+    path: PathBuf,
+    root: PathBuf,
+    assets: Vec<Rc<RefCell<Fingerprint>>>,
+}
+
+impl RewriteRefs {
+    pub fn new<P: AsRef<Path>>(source: P, path: P, root: P) -> Self {
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
+            root: normalize(root),
+            assets: vec![],
+        }
+    }
+
+    /// Adds a fingerprinted asset whose references are rewritten.
+    pub fn asset(mut self, asset: Rc<RefCell<Fingerprint>>) -> Self {
+        self.assets.push(asset);
+        self
+    }
+
+    // returns the reference with its file name replaced, if it names an asset
+    fn rewrite(&self, reference: &str) -> Option<String> {
+        if reference.contains(':') || reference.starts_with("//") {
+            return None;
+        }
+        let end = reference.find(['?', '#']).unwrap_or(reference.len());
+        let (location, suffix) = reference.split_at(end);
+        let mut file = match location.strip_prefix('/') {
+            Some(_) => self.root.clone(),
+            None => self.path.parent().unwrap_or(Path::new("")).to_path_buf(),
+        };
+        for segment in location.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => {
+                    file.pop();
+                }
+                segment => file.push(segment),
+            }
+        }
+        let file = normalize(file);
+
+        let asset = self
+            .assets
+            .iter()
+            .find(|asset| normalize(asset.borrow().logical()) == file)?;
+        let asset = asset.borrow();
+        let name = asset.fingerprinted()?.file_name()?.to_string_lossy();
+        let directory = match location.rfind('/') {
+            Some(index) => &location[..=index],
+            None => "",
+        };
+        Some(format!("{}{}{}", directory, name, suffix))
+    }
+}
+
+impl PartialEq for RewriteRefs {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source && self.path == other.path && self.root == other.root
+    }
+}
+
+impl Build for RewriteRefs {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        if let Some(node) = builder.producer(&self.source) {
+            builder.label(&node, "source");
+            dependencies.push(node);
+        }
+        for asset in self.assets.clone() {
+            let node = builder.require_ref(asset)?;
+            builder.label(&node, "asset");
+            dependencies.push(node);
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let html = std::fs::read_to_string(&self.source)
+            .map_err(|e| format!("failed to read {:?}: {}", self.source, e))?;
+
+        let attribute =
+            Regex::new(r#"(?i)(\b(?:href|src)\s*=\s*)(?:"([^"]*)"|'([^']*)')"#).unwrap();
+        let html = attribute.replace_all(&html, |attribute: &Captures| {
+            let reference = attribute.get(2).or(attribute.get(3)).unwrap().as_str();
+            match self.rewrite(reference) {
+                Some(reference) => format!("{}\"{}\"", &attribute[1], reference),
+                None => attribute[0].to_string(),
+            }
+        });
+        std::fs::write(&self.path, html.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{scratch_dir, StringFile};
+
+    #[test]
+    fn test_rewrite_refs() {
+        let dir = scratch_dir("rewrite");
+        let site = dir.join("site");
+        let html = concat!(
+            "<link rel=\"stylesheet\" href=\"/css/style.css?v=1\">",
+            "<link rel=\"preload\" href='../css/style.css#top'>",
+            "<script src=\"/js/app.js\"></script>",
+            "<a href=\"https://example.com/css/style.css\">",
+            "<img src=\"/css/other.css\">",
+        );
+        let mut builder = Builder::new();
+        builder
+            .require(StringFile::new(dir.join("pages/index.html"), html))
+            .unwrap();
+        let asset = Fingerprint::new(dir.join("style.css"), site.join("css/style.css"));
+        let asset = Rc::new(RefCell::new(asset));
+        let rewrite = RewriteRefs::new(
+            dir.join("pages/index.html"),
+            site.join("blog/index.html"),
+            site.clone(),
+        )
+        .asset(asset.clone());
+        std::fs::write(dir.join("style.css"), "body {}").unwrap();
+        builder.require(rewrite).unwrap();
+        builder.generate().unwrap();
+
+        let fingerprinted = asset.borrow().fingerprinted().unwrap().to_path_buf();
+        let name = fingerprinted.file_name().unwrap().to_string_lossy();
+        let page = std::fs::read_to_string(site.join("blog/index.html")).unwrap();
+
+        // absolute and relative references keep their directory and suffix
+        assert!(page.contains(&format!("href=\"/css/{}?v=1\"", name)));
+        assert!(page.contains(&format!("href=\"../css/{}#top\"", name)));
+
+        // other references are left alone
+        assert!(page.contains("src=\"/js/app.js\""));
+        assert!(page.contains("href=\"https://example.com/css/style.css\""));
+        assert!(page.contains("src=\"/css/other.css\""));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,180 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::Directory,
+};
+
+/// A group of `Allow` and `Disallow` rules for one user agent in a
+/// `Robots` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RobotsRule {
+    user_agent: String,
+    allow: Vec<String>,
+    disallow: Vec<String>,
+}
+
+impl RobotsRule {
+    pub fn new(user_agent: &str) -> Self {
+        Self {
+            user_agent: user_agent.to_string(),
+            allow: vec![],
+            disallow: vec![],
+        }
+    }
+
+    pub fn allow(mut self, path: &str) -> Self {
+        self.allow.push(path.to_string());
+        self
+    }
+
+    pub fn disallow(mut self, path: &str) -> Self {
+        self.disallow.push(path.to_string());
+        self
+    }
+}
+
+/// Writes a `robots.txt` from a list of rules and an optional sitemap URL.
+#[derive(Debug)]
+pub struct Robots {
+    id: Option<u64>,
+    path: PathBuf,
+    rules: Vec<RobotsRule>,
+    sitemap: Option<String>,
+}
+
+impl Robots {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            id: None,
+            path: path.as_ref().to_path_buf(),
+            rules: vec![],
+            sitemap: None,
+        }
+    }
+
+    pub fn rule(mut self, rule: RobotsRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn sitemap(mut self, url: &str) -> Self {
+        self.sitemap = Some(url.to_string());
+        self
+    }
+}
+
+impl PartialEq for Robots {
+    fn eq(&self, other: &Self) -> bool {
+        self.rules == other.rules && self.sitemap == other.sitemap
+    }
+}
+
+impl Build for Robots {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                Ok(vec![directory])
+            }
+            None => Ok(vec![]),
+        }
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut groups = vec![];
+        for rule in &self.rules {
+            let mut group = format!("User-agent: {}\n", rule.user_agent);
+            for path in &rule.allow {
+                group.push_str(&format!("Allow: {}\n", path));
+            }
+            for path in &rule.disallow {
+                group.push_str(&format!("Disallow: {}\n", path));
+            }
+            // a group without rules allows everything
+            if rule.allow.is_empty() && rule.disallow.is_empty() {
+                group.push_str("Disallow:\n");
+            }
+            groups.push(group);
+        }
+        if let Some(sitemap) = &self.sitemap {
+            groups.push(format!("Sitemap: {}\n", sitemap));
+        }
+        std::fs::write(&self.path, groups.join("\n"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::scratch_dir;
+
+    #[test]
+    fn test_robots() {
+        let dir = scratch_dir("robots");
+        let mut robots = Robots::new(dir.join("robots.txt"))
+            .rule(
+                RobotsRule::new("*")
+                    .allow("/public/")
+                    .disallow("/drafts/")
+                    .disallow("/tmp/"),
+            )
+            .rule(RobotsRule::new("Googlebot"))
+            .sitemap("https://example.com/sitemap.xml");
+        robots.generate().unwrap();
+
+        let text = std::fs::read_to_string(dir.join("robots.txt")).unwrap();
+        assert_eq!(
+            text,
+            "User-agent: *\n\
+             Allow: /public/\n\
+             Disallow: /drafts/\n\
+             Disallow: /tmp/\n\
+             \n\
+             User-agent: Googlebot\n\
+             Disallow:\n\
+             \n\
+             Sitemap: https://example.com/sitemap.xml\n"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_robots_without_sitemap() {
+        let dir = scratch_dir("robots-without-sitemap");
+        let mut robots =
+            Robots::new(dir.join("robots.txt")).rule(RobotsRule::new("*").disallow("/"));
+        robots.generate().unwrap();
+
+        let text = std::fs::read_to_string(dir.join("robots.txt")).unwrap();
+        assert_eq!(text, "User-agent: *\nDisallow: /\n");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,269 @@
+use regex::Regex;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::OnceLock;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::Directory,
+};
+
+/// Compiles an SCSS entry file to CSS.
+///
+/// Files loaded with `@use`, `@forward` or `@import` are resolved relative
+/// to the importing file and then the include paths. Any of them produced
+/// by other resources are depended upon, so they are generated before the
+/// stylesheet is compiled. Imports are only followed through files which
+/// already exist, so the imports of a produced file are not known.
+#[derive(Debug)]
+pub struct CompileScss {
+    id: Option<u64>,
+    source: PathBuf,
+    path: PathBuf,
+    include_paths: Vec<PathBuf>,
+}
+
+impl CompileScss {
+    pub fn new<P: AsRef<Path>>(source: P, path: P) -> Self {
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
+            include_paths: vec![],
+        }
+    }
+
+    /// Adds a directory searched for imports which are not found relative to
+    /// the importing file.
+    pub fn include_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.include_paths.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Returns the candidate paths of every file the entry imports, directly
+    /// or transitively. Imports are followed through files which exist.
+    fn imports(&self) -> Vec<PathBuf> {
+        static STATEMENT: OnceLock<Regex> = OnceLock::new();
+        static QUOTED: OnceLock<Regex> = OnceLock::new();
+        let statement =
+            STATEMENT.get_or_init(|| Regex::new(r#"@(?:use|forward|import)\s+([^;]+);"#).unwrap());
+        let quoted = QUOTED.get_or_init(|| Regex::new(r#"["']([^"']+)["']"#).unwrap());
+
+        let mut imports: Vec<PathBuf> = vec![];
+        let mut pending = vec![self.source.clone()];
+        while let Some(file) = pending.pop() {
+            let text = match std::fs::read_to_string(&file) {
+                Ok(text) => text,
+                Err(_) => continue,
+            };
+            let text = strip_comments(&text);
+            let directory = file.parent().unwrap_or(Path::new(""));
+            for captures in statement.captures_iter(&text) {
+                for url in quoted.captures_iter(&captures[1]) {
+                    let url = &url[1];
+                    if url.starts_with("sass:") || url.contains("://") {
+                        continue;
+                    }
+                    let mut directories = vec![directory.to_path_buf()];
+                    directories.extend(self.include_paths.iter().cloned());
+                    for directory in directories {
+                        for candidate in candidates(&directory.join(url)) {
+                            if !imports.contains(&candidate) {
+                                imports.push(candidate.clone());
+                                pending.push(candidate);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        imports
+    }
+}
+
+impl PartialEq for CompileScss {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source && self.include_paths == other.include_paths
+    }
+}
+
+impl Build for CompileScss {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        if let Some(node) = builder.producer(&self.source) {
+            builder.label(&node, "source");
+            dependencies.push(node);
+        }
+        for import in self.imports() {
+            if let Some(node) = builder.producer(&import) {
+                builder.label(&node, "import");
+                dependencies.push(node);
+            }
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let options = grass::Options::default().load_paths(&self.include_paths);
+        let css = grass::from_path(&self.source, &options)
+            .map_err(|e| format!("failed to compile {:?}: {}", self.source, e))?;
+        std::fs::write(&self.path, css)?;
+        Ok(())
+    }
+}
+
+// removes `//` and `/* */` comments, leaving quoted strings which contain
+// them untouched
+fn strip_comments(text: &str) -> String {
+    static COMMENT: OnceLock<Regex> = OnceLock::new();
+    let comment = COMMENT.get_or_init(|| {
+        Regex::new(r#"("(?:[^"\\\n]|\\.)*"|'(?:[^'\\\n]|\\.)*')|//[^\n]*|/\*(?s:.*?)\*/"#).unwrap()
+    });
+    comment
+        .replace_all(text, |captures: &regex::Captures| {
+            captures
+                .get(1)
+                .map(|quoted| quoted.as_str().to_string())
+                .unwrap_or_default()
+        })
+        .into_owned()
+}
+
+// files which Sass may load for an import of `path`, with or without a
+// leading underscore, as a module directory index, or with an extension
+fn candidates(path: &Path) -> Vec<PathBuf> {
+    let name = match path.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => return vec![],
+    };
+    let mut candidates = vec![];
+    for extension in ["scss", "sass", "css"] {
+        if path.extension().is_some_and(|e| e == extension) {
+            candidates.push(path.to_path_buf());
+            candidates.push(path.with_file_name(format!("_{}", name)));
+            return candidates;
+        }
+    }
+    for extension in ["scss", "sass", "css"] {
+        candidates.push(path.with_file_name(format!("{}.{}", name, extension)));
+        candidates.push(path.with_file_name(format!("_{}.{}", name, extension)));
+        candidates.push(path.join(format!("_index.{}", extension)));
+        candidates.push(path.join(format!("index.{}", extension)));
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{scratch_dir, StringFile};
+
+    #[test]
+    fn test_strip_comments() {
+        let text = "a // b\nc /* d\ne */ f \"g // h\" 'i /* j */'";
+        assert_eq!(strip_comments(text), "a \nc  f \"g // h\" 'i /* j */'");
+    }
+
+    #[test]
+    fn test_imports() {
+        let dir = scratch_dir("scss-imports");
+        std::fs::create_dir_all(dir.join("lib/theme")).unwrap();
+        std::fs::write(
+            dir.join("main.scss"),
+            [
+                "@use \"sass:math\";",
+                "@use \"colors\" as c;",
+                "@forward 'mixins';",
+                "@import \"reset\", 'layout';",
+                "// @import \"commented\";",
+                "/* @use \"blocked\"; */",
+                "@use \"theme\";",
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+        std::fs::write(dir.join("_colors.scss"), "@use 'nested';").unwrap();
+        std::fs::write(dir.join("_nested.scss"), "").unwrap();
+        std::fs::write(dir.join("lib/theme/_index.scss"), "").unwrap();
+
+        let scss = CompileScss::new(dir.join("main.scss"), dir.join("main.css"))
+            .include_path(dir.join("lib"));
+        let imports = scss.imports();
+        let imported = |path: &str| imports.contains(&dir.join(path));
+
+        // partials, quoted lists and modules in include paths are found
+        assert!(imported("_colors.scss"));
+        assert!(imported("_mixins.scss"));
+        assert!(imported("reset.scss"));
+        assert!(imported("_layout.scss"));
+        assert!(imported("lib/theme/_index.scss"));
+
+        // imports are followed through existing files
+        assert!(imported("_nested.scss"));
+
+        // commented out and built in modules are not
+        assert!(!imports.iter().any(|path| {
+            let path = path.to_string_lossy();
+            path.contains("commented") || path.contains("blocked") || path.contains("math")
+        }));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_compile_scss_import_required_later() {
+        let dir = scratch_dir("scss-later");
+        std::fs::write(
+            dir.join("main.scss"),
+            "@use 'vars';\np { color: vars.$color; }",
+        )
+        .unwrap();
+
+        // the partial is produced by a resource required after the stylesheet
+        let mut builder = Builder::new();
+        builder
+            .require(CompileScss::new(
+                dir.join("main.scss"),
+                dir.join("main.css"),
+            ))
+            .unwrap();
+        builder
+            .require(StringFile::new(dir.join("_vars.scss"), "$color: red;"))
+            .unwrap();
+        builder.generate().unwrap();
+
+        let css = std::fs::read_to_string(dir.join("main.css")).unwrap();
+        assert!(css.contains("color: red"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
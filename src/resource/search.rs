@@ -0,0 +1,272 @@
+use regex::Regex;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::OnceLock;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::Directory,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+struct Page {
+    url: String,
+    source: PathBuf,
+}
+
+/// Writes a JSON search index of the form
+/// `[{ "url": ..., "title": ..., "text": ... }]` from a set of HTML pages.
+///
+/// Pages which are produced by other resources are depended upon, so the
+/// index is generated after all of them.
+#[derive(Debug)]
+pub struct SearchIndex {
+    id: Option<u64>,
+    path: PathBuf,
+    pages: Vec<Page>,
+    title_tag: String,
+    content_tag: Option<String>,
+    max_text_length: Option<usize>,
+}
+
+impl SearchIndex {
+    pub fn builder<P: AsRef<Path>>(path: P) -> SearchIndexBuilder {
+        SearchIndexBuilder::new(path)
+    }
+}
+
+impl PartialEq for SearchIndex {
+    fn eq(&self, other: &Self) -> bool {
+        self.pages == other.pages
+            && self.title_tag == other.title_tag
+            && self.content_tag == other.content_tag
+            && self.max_text_length == other.max_text_length
+    }
+}
+
+impl Build for SearchIndex {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        for page in &self.pages {
+            if let Some(node) = builder.producer(&page.source) {
+                builder.label(&node, "page");
+                dependencies.push(node);
+            }
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let title = element_pattern(&self.title_tag)?;
+        let content = match &self.content_tag {
+            Some(tag) => Some(element_pattern(tag)?),
+            None => None,
+        };
+
+        let mut entries = vec![];
+        for page in &self.pages {
+            let html = std::fs::read_to_string(&page.source)
+                .map_err(|e| format!("failed to read {:?}: {}", page.source, e))?;
+            let page_title = match title.captures(&html) {
+                Some(captures) => strip_tags(&captures[1]),
+                None => String::new(),
+            };
+            let body = match content.as_ref().and_then(|c| c.captures(&html)) {
+                Some(captures) => captures[1].to_string(),
+                None => html.clone(),
+            };
+            let mut text = strip_tags(&body);
+            if let Some(max) = self.max_text_length {
+                if let Some((index, _)) = text.char_indices().nth(max) {
+                    text.truncate(index);
+                    // a cut between words would leave a trailing space
+                    text.truncate(text.trim_end().len());
+                }
+            }
+            entries.push(serde_json::json!({
+                "url": page.url,
+                "title": page_title,
+                "text": text,
+            }));
+        }
+
+        let json = serde_json::to_string(&entries)?;
+        std::fs::write(&self.path, json)?;
+        Ok(())
+    }
+}
+
+pub struct SearchIndexBuilder {
+    path: PathBuf,
+    pages: Vec<Page>,
+    title_tag: String,
+    content_tag: Option<String>,
+    max_text_length: Option<usize>,
+}
+
+impl SearchIndexBuilder {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            pages: vec![],
+            title_tag: String::from("title"),
+            content_tag: None,
+            max_text_length: None,
+        }
+    }
+
+    /// Adds the HTML file at `source` to the index under `url`.
+    pub fn page<P: AsRef<Path>>(mut self, url: &str, source: P) -> Self {
+        self.pages.push(Page {
+            url: url.to_string(),
+            source: source.as_ref().to_path_buf(),
+        });
+        self
+    }
+
+    /// Tag whose text is used as the page title. Defaults to `title`.
+    pub fn title_tag(mut self, tag: &str) -> Self {
+        self.title_tag = tag.to_string();
+        self
+    }
+
+    /// Tag whose text is indexed, e.g. `main`. Defaults to the whole page.
+    pub fn content_tag(mut self, tag: &str) -> Self {
+        self.content_tag = Some(tag.to_string());
+        self
+    }
+
+    /// Maximum number of characters of text indexed per page.
+    pub fn max_text_length(mut self, length: usize) -> Self {
+        self.max_text_length = Some(length);
+        self
+    }
+
+    pub fn build(self) -> SearchIndex {
+        SearchIndex {
+            id: None,
+            path: self.path,
+            pages: self.pages,
+            title_tag: self.title_tag,
+            content_tag: self.content_tag,
+            max_text_length: self.max_text_length,
+        }
+    }
+}
+
+// matches the first element with the given tag, capturing its inner html
+fn element_pattern(tag: &str) -> Result<Regex, regex::Error> {
+    let tag = regex::escape(tag);
+    Regex::new(&format!(r"(?is)<{tag}(?:\s[^>]*)?>(.*?)</{tag}\s*>"))
+}
+
+// a simple tag stripper
+// removes scripts, styles, comments and tags then collapses whitespace
+fn strip_tags(html: &str) -> String {
+    static HIDDEN: OnceLock<Regex> = OnceLock::new();
+    static TAGS: OnceLock<Regex> = OnceLock::new();
+    let hidden = HIDDEN.get_or_init(|| {
+        Regex::new(r"(?is)<script.*?</script\s*>|<style.*?</style\s*>|<!--.*?-->").unwrap()
+    });
+    let tags = TAGS.get_or_init(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+    let text = hidden.replace_all(html, " ");
+    let text = tags.replace_all(&text, " ");
+    let text = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{scratch_dir, StringFile};
+
+    #[test]
+    fn test_strip_tags() {
+        let html = "<p>Tom &amp; <b>Jerry</b></p>\n<script>var x;</script><!-- note -->  end";
+        assert_eq!(strip_tags(html), "Tom & Jerry end");
+    }
+
+    #[test]
+    fn test_search_index_truncation() {
+        let dir = scratch_dir("search");
+        let html = "<title>Home</title><main>hello wörld again</main>";
+        std::fs::write(dir.join("index.html"), html).unwrap();
+
+        let index = |length: usize| {
+            let mut index = SearchIndex::builder(dir.join("search.json"))
+                .page("/", dir.join("index.html"))
+                .content_tag("main")
+                .max_text_length(length)
+                .build();
+            index.generate().unwrap();
+            let json = std::fs::read_to_string(dir.join("search.json")).unwrap();
+            let entries: serde_json::Value = serde_json::from_str(&json).unwrap();
+            entries[0]["text"].as_str().unwrap().to_string()
+        };
+
+        // lengths count characters, and a cut between words is trimmed
+        assert_eq!(index(8), "hello wö");
+        assert_eq!(index(6), "hello");
+        assert_eq!(index(100), "hello wörld again");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_search_index_page_required_later() {
+        let dir = scratch_dir("search-later");
+        let index = SearchIndex::builder(dir.join("search.json"))
+            .page("/", dir.join("index.html"))
+            .build();
+        let mut builder = Builder::new();
+        builder.require(index).unwrap();
+        builder
+            .require(StringFile::new(
+                dir.join("index.html"),
+                "<title>Home</title><p>welcome</p>",
+            ))
+            .unwrap();
+
+        // the page is still written before it is read
+        builder.generate().unwrap();
+        let json = std::fs::read_to_string(dir.join("search.json")).unwrap();
+        assert!(json.contains("\"title\":\"Home\""));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
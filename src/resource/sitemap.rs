@@ -0,0 +1,199 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{normalize, Build, Builder, Node, Registration},
+    resource::{xml::escape, Directory},
+};
+
+/// Writes a `sitemap.xml` listing every HTML page registered under `root`.
+///
+/// Pages are taken from the outputs registered with the builder when the
+/// sitemap's dependencies are resolved, so it must be required after all
+/// of the pages. Requiring a page after the sitemap fails rather than
+/// leaving it out. Each page is depended upon, and its URL is `base_url`
+/// joined with its path relative to `root`.
+#[derive(Debug)]
+pub struct Sitemap {
+    id: Option<u64>,
+    base_url: String,
+    root: PathBuf,
+    path: PathBuf,
+    pages: Vec<PathBuf>,
+}
+
+impl Sitemap {
+    pub fn new<P: AsRef<Path>>(base_url: &str, root: P, path: P) -> Self {
+        Self {
+            id: None,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            root: normalize(root),
+            path: path.as_ref().to_path_buf(),
+            pages: vec![],
+        }
+    }
+
+    fn url(&self, page: &Path) -> String {
+        let relative = page.strip_prefix(&self.root).unwrap_or(page);
+        let segments: Vec<String> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        format!("{}/{}", self.base_url, segments.join("/"))
+    }
+}
+
+impl PartialEq for Sitemap {
+    fn eq(&self, other: &Self) -> bool {
+        self.base_url == other.base_url && self.root == other.root
+    }
+}
+
+impl Build for Sitemap {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+
+        let pages = builder.outputs_under(&self.root, |path| {
+            path.extension()
+                .is_some_and(|extension| extension == "html")
+        });
+        for page in &pages {
+            if let Some(node) = builder.producer(page) {
+                builder.label(&node, "page");
+                dependencies.push(node);
+            }
+        }
+        self.pages = pages;
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+        for page in &self.pages {
+            let url = escape(&self.url(page));
+            xml.push_str(&format!("  <url><loc>{}</loc></url>\n", url));
+        }
+        xml.push_str("</urlset>\n");
+        std::fs::write(&self.path, xml)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{scratch_dir, StringFile};
+
+    #[test]
+    fn test_sitemap() {
+        let dir = scratch_dir("sitemap");
+        let mut builder = Builder::new();
+        builder
+            .require(StringFile::new(dir.join("index.html"), "home"))
+            .unwrap();
+        builder
+            .require(StringFile::new(dir.join("blog/a&b.html"), "post"))
+            .unwrap();
+        builder
+            .require(StringFile::new(dir.join("style.css"), "body {}"))
+            .unwrap();
+        let sitemap = Sitemap::new("https://example.com/", &dir, &dir.join("sitemap.xml"));
+        builder.require(sitemap).unwrap();
+        builder.generate().unwrap();
+
+        let xml = std::fs::read_to_string(dir.join("sitemap.xml")).unwrap();
+        assert!(xml.contains("<loc>https://example.com/blog/a&amp;b.html</loc>"));
+        assert!(xml.contains("<loc>https://example.com/index.html</loc>"));
+        assert!(!xml.contains("style.css"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sitemap_page_required_later() {
+        let dir = scratch_dir("sitemap-later");
+        let mut builder = Builder::new();
+        builder
+            .require(StringFile::new(dir.join("index.html"), "home"))
+            .unwrap();
+        let sitemap = Sitemap::new("https://example.com", &dir, &dir.join("sitemap.xml"));
+        builder.require(sitemap).unwrap();
+
+        // the late page fails instead of being left out
+        let error = builder
+            .require(StringFile::new(dir.join("about.html"), "about"))
+            .unwrap_err();
+        assert!(error.to_string().contains("about.html"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sitemap_relative_root() {
+        let mut builder = Builder::new();
+        builder
+            .require(StringFile::new("index.html", "home"))
+            .unwrap();
+        builder
+            .require(StringFile::new("./blog/post.html", "post"))
+            .unwrap();
+
+        // the current directory contains every page
+        let sitemap = Rc::new(RefCell::new(Sitemap::new(
+            "https://example.com",
+            ".",
+            "sitemap.xml",
+        )));
+        builder.require_ref(sitemap.clone()).unwrap();
+        let sitemap = sitemap.borrow();
+        let urls: Vec<String> = sitemap.pages.iter().map(|page| sitemap.url(page)).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/blog/post.html",
+                "https://example.com/index.html"
+            ]
+        );
+
+        // and a relative root is matched however it is spelled
+        let sitemap = Rc::new(RefCell::new(Sitemap::new(
+            "https://example.com",
+            "./blog/",
+            "blog.xml",
+        )));
+        builder.require_ref(sitemap.clone()).unwrap();
+        let sitemap = sitemap.borrow();
+        let urls: Vec<String> = sitemap.pages.iter().map(|page| sitemap.url(page)).collect();
+        assert_eq!(urls, vec!["https://example.com/post.html"]);
+    }
+}
@@ -0,0 +1,117 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::ResizeImage,
+};
+
+/// Writes resized variants of an image at several widths for responsive
+/// images, e.g. `photo-320w.jpg` and `photo-640w.jpg` for `photo.jpg`.
+///
+/// Each variant is a `ResizeImage` depended upon by this resource, so
+/// images are not enlarged and the source should be at least as wide as the
+/// largest width. `srcset` returns the value of the `srcset` attribute.
+#[derive(Debug)]
+pub struct Srcset {
+    id: Option<u64>,
+    source: PathBuf,
+    path: PathBuf,
+    widths: Vec<u32>,
+    href_prefix: String,
+    variants: Vec<Rc<RefCell<ResizeImage>>>,
+}
+
+impl Srcset {
+    pub fn new<P: AsRef<Path>>(source: P, path: P, widths: Vec<u32>) -> Self {
+        let variants = widths
+            .iter()
+            .map(|width| {
+                let variant = ResizeImage::new(source.as_ref(), &variant(path.as_ref(), *width))
+                    .max_width(*width);
+                Rc::new(RefCell::new(variant))
+            })
+            .collect();
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
+            widths,
+            href_prefix: String::new(),
+            variants,
+        }
+    }
+
+    /// Sets the URL prefix of the variants as served, e.g. `/images/`.
+    /// By default the variants are referenced by file name alone.
+    pub fn href_prefix(mut self, href_prefix: &str) -> Self {
+        self.href_prefix = href_prefix.to_string();
+        self
+    }
+
+    /// Returns the `srcset` attribute value listing each variant.
+    pub fn srcset(&self) -> String {
+        let candidates: Vec<String> = self
+            .widths
+            .iter()
+            .map(|width| {
+                let variant = variant(&self.path, *width);
+                let name = variant.file_name().unwrap_or_default().to_string_lossy();
+                format!("{}{} {}w", self.href_prefix, name, width)
+            })
+            .collect();
+        candidates.join(", ")
+    }
+}
+
+impl PartialEq for Srcset {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+            && self.widths == other.widths
+            && self.href_prefix == other.href_prefix
+    }
+}
+
+impl Build for Srcset {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Virtual())
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = vec![];
+        for variant in self.variants.clone() {
+            let node = builder.require_ref(variant)?;
+            builder.label(&node, "variant");
+            dependencies.push(node);
+        }
+        Ok(dependencies)
+    }
+}
+
+// inserts the width before the extension, e.g. `photo.jpg` to `photo-320w.jpg`
+fn variant(path: &Path, width: u32) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}-{}w.{}", stem, width, extension.to_string_lossy()),
+        None => format!("{}-{}w", stem, width),
+    };
+    path.with_file_name(name)
+}
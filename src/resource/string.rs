@@ -0,0 +1,72 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::Directory,
+};
+
+/// Writes the given text to a file.
+#[derive(Debug)]
+pub struct StringFile {
+    id: Option<u64>,
+    path: PathBuf,
+    content: String,
+}
+
+impl StringFile {
+    pub fn new<P: AsRef<Path>>(path: P, content: &str) -> Self {
+        Self {
+            id: None,
+            path: path.as_ref().to_path_buf(),
+            content: content.to_string(),
+        }
+    }
+}
+
+impl PartialEq for StringFile {
+    fn eq(&self, other: &Self) -> bool {
+        self.content == other.content
+    }
+}
+
+impl Build for StringFile {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                Ok(vec![directory])
+            }
+            None => Ok(vec![]),
+        }
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(&self.path, &self.content)
+            .map_err(|e| format!("failed to write {:?}: {}", self.path, e))?;
+        Ok(())
+    }
+}
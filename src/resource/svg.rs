@@ -0,0 +1,160 @@
+use regex::Regex;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::Directory,
+};
+
+/// Writes a smaller copy of an SVG file.
+///
+/// This is a conservative pass which removes comments, `<metadata>`,
+/// Inkscape and Sodipodi editor data, and indentation between tags. Inside
+/// `<text>` elements whitespace is collapsed to single spaces instead, so
+/// rendered text is unchanged. Paths and other geometry are left alone.
+#[derive(Debug)]
+pub struct OptimizeSvg {
+    id: Option<u64>,
+    source: PathBuf,
+    path: PathBuf,
+}
+
+impl OptimizeSvg {
+    pub fn new<P: AsRef<Path>>(source: P, path: P) -> Self {
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl PartialEq for OptimizeSvg {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Build for OptimizeSvg {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        if let Some(node) = builder.producer(&self.source) {
+            builder.label(&node, "source");
+            dependencies.push(node);
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let svg = std::fs::read_to_string(&self.source)
+            .map_err(|e| format!("failed to read {:?}: {}", self.source, e))?;
+        std::fs::write(&self.path, optimize(&svg))?;
+        Ok(())
+    }
+}
+
+fn optimize(svg: &str) -> String {
+    let removals = [
+        r"<!--[\s\S]*?-->",
+        r"<metadata\b[^>]*/>",
+        r"<metadata\b[\s\S]*?</metadata>",
+        r"<(?:sodipodi|inkscape):[\w.-]+\b[^>]*/>",
+        r"<(sodipodi|inkscape):namedview\b[\s\S]*?</(?:sodipodi|inkscape):namedview>",
+        r#"\s+(?:sodipodi|inkscape):[\w.-]+\s*=\s*(?:"[^"]*"|'[^']*')"#,
+        r#"\s+xmlns:(?:sodipodi|inkscape)\s*=\s*(?:"[^"]*"|'[^']*')"#,
+    ];
+    let mut svg = svg.to_string();
+    for removal in removals {
+        svg = Regex::new(removal)
+            .unwrap()
+            .replace_all(&svg, "")
+            .into_owned();
+    }
+
+    // whitespace between tags is only significant inside text, and the
+    // segments around text elements are bounded by tags
+    let text = Regex::new(r"<text\b[\s\S]*?</text>").unwrap();
+    let between = Regex::new(r">\s+<").unwrap();
+    let whitespace = Regex::new(r"\s+").unwrap();
+    let mut optimized = String::with_capacity(svg.len());
+    let mut offset = 0;
+    for element in text.find_iter(&svg) {
+        let outside = svg[offset..element.start()].trim();
+        optimized.push_str(&between.replace_all(outside, "><"));
+        optimized.push_str(&whitespace.replace_all(element.as_str(), " "));
+        offset = element.end();
+    }
+    optimized.push_str(&between.replace_all(svg[offset..].trim(), "><"));
+    optimized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_optimize_editor_data() {
+        let svg = r##"<?xml version="1.0"?>
+<!-- Created with Inkscape -->
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" inkscape:version="1.3" width="10">
+  <metadata><rdf:RDF></rdf:RDF></metadata>
+  <sodipodi:namedview id="view" pagecolor="#fff"><inkscape:page x="0"/></sodipodi:namedview>
+  <inkscape:grid/>
+  <path inkscape:label="box" d="M 0 0 L 10 10"/>
+</svg>
+"##;
+        assert_eq!(
+            optimize(svg),
+            concat!(
+                r#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg" width="10">"#,
+                r#"<path d="M 0 0 L 10 10"/></svg>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_optimize_text() {
+        // whitespace in text is collapsed rather than removed
+        let svg = "<svg>\n  <text x=\"0\">\n    Hello   <tspan>big</tspan>\n    world\n  </text>\n  <g/>\n</svg>";
+        assert_eq!(
+            optimize(svg),
+            "<svg><text x=\"0\"> Hello <tspan>big</tspan> world </text><g/></svg>"
+        );
+    }
+
+    #[test]
+    fn test_optimize_unchanged() {
+        let svg = r#"<svg viewBox="0 0 10 10"><path d="M0 0h10v10z"/></svg>"#;
+        assert_eq!(optimize(svg), svg);
+    }
+}
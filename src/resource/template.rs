@@ -0,0 +1,284 @@
+use encoding_rs::Encoding;
+use regex::Regex;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::{copy::read_text, Directory},
+};
+
+/// A resource which provides the value of a template variable once it has
+/// been generated, e.g. the integrity hash of an asset.
+pub trait Variable: Build {
+    /// Returns the value once the resource has been generated.
+    fn value(&self) -> Option<String>;
+}
+
+// a variable source, held both as a resource to require and as a variable
+type Source = (String, Rc<RefCell<dyn Build>>, Rc<RefCell<dyn Variable>>);
+
+/// Renders a template file by replacing `{{ key }}` placeholders with the
+/// values of variables.
+///
+/// A placeholder without a matching variable is an error. Depends on the
+/// resource producing the template, if one is registered, and on the
+/// resources providing variables.
+#[derive(Debug)]
+pub struct RenderTemplate {
+    id: Option<u64>,
+    template: PathBuf,
+    path: PathBuf,
+    variables: HashMap<String, String>,
+    sources: Vec<Source>,
+    encoding: Option<&'static Encoding>,
+}
+
+impl RenderTemplate {
+    pub fn new<P: AsRef<Path>>(template: P, path: P, variables: HashMap<String, String>) -> Self {
+        Self {
+            id: None,
+            template: template.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
+            variables,
+            sources: vec![],
+            encoding: None,
+        }
+    }
+
+    /// Decodes the template from `encoding` rather than UTF-8. The output
+    /// is written as UTF-8, and malformed input is reported as an error.
+    pub fn with_encoding(mut self, encoding: &'static Encoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// Sets the variable `key` to the value of a resource once it has been
+    /// generated, replacing any variable of the same name.
+    pub fn variable<T: Variable + 'static>(mut self, key: &str, source: Rc<RefCell<T>>) -> Self {
+        self.sources.push((key.to_string(), source.clone(), source));
+        self
+    }
+}
+
+impl PartialEq for RenderTemplate {
+    fn eq(&self, other: &Self) -> bool {
+        self.template == other.template
+            && self.variables == other.variables
+            && self.encoding == other.encoding
+            && self.sources.len() == other.sources.len()
+            && self.sources.iter().zip(&other.sources).all(
+                |((key, source, _), (other_key, other, _))| {
+                    key == other_key && source.borrow().equals(other.clone())
+                },
+            )
+    }
+}
+
+impl Build for RenderTemplate {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        if let Some(node) = builder.producer(&self.template) {
+            builder.label(&node, "template");
+            dependencies.push(node);
+        }
+        for (_, source, _) in &self.sources {
+            let node = builder.require_dyn(source.clone())?;
+            builder.label(&node, "variable");
+            dependencies.push(node);
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let template = read_text(&self.template, self.encoding)?;
+
+        let mut variables = self.variables.clone();
+        for (key, source, variable) in &self.sources {
+            let value = match variable.borrow().value() {
+                Some(value) => value,
+                None => return Err(format!("{:?} was not generated", source.borrow()).into()),
+            };
+            variables.insert(key.clone(), value);
+        }
+
+        let placeholder = Regex::new(r"\{\{\s*([\w.-]+)\s*\}\}").unwrap();
+        let mut unresolved: Vec<String> = vec![];
+        let substitute = |captures: &regex::Captures| match variables.get(&captures[1]) {
+            Some(value) => value.clone(),
+            None => {
+                if !unresolved.contains(&captures[1].to_string()) {
+                    unresolved.push(captures[1].to_string());
+                }
+                captures[0].to_string()
+            }
+        };
+        let rendered = placeholder.replace_all(&template, substitute);
+        if !unresolved.is_empty() {
+            let message = format!(
+                "unresolved placeholders in {:?}: {}",
+                self.template,
+                unresolved.join(", ")
+            );
+            return Err(message.into());
+        }
+
+        std::fs::write(&self.path, rendered.as_ref())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::{scratch_dir, sri, Integrity, SriAlgorithm, StringFile};
+
+    fn variables(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_render_template() {
+        let dir = scratch_dir("template");
+        std::fs::write(dir.join("page.html"), "<h1>{{title}}</h1>{{ site.name }}").unwrap();
+
+        let variables = variables(&[("title", "Hello"), ("site.name", "blanket")]);
+        let mut template =
+            RenderTemplate::new(dir.join("page.html"), dir.join("index.html"), variables);
+        template.generate().unwrap();
+
+        let html = std::fs::read_to_string(dir.join("index.html")).unwrap();
+        assert_eq!(html, "<h1>Hello</h1>blanket");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_render_template_encoding() {
+        let dir = scratch_dir("template-encoding");
+        std::fs::write(dir.join("page.html"), b"caf\xe9 {{ name }}").unwrap();
+
+        // the template is decoded and the output written as UTF-8
+        let variables = variables(&[("name", "blanket")]);
+        let mut template =
+            RenderTemplate::new(dir.join("page.html"), dir.join("index.html"), variables)
+                .with_encoding(encoding_rs::WINDOWS_1252);
+        template.generate().unwrap();
+        let html = std::fs::read_to_string(dir.join("index.html")).unwrap();
+        assert_eq!(html, "café blanket");
+
+        // and without an encoding it must be UTF-8
+        let mut template = RenderTemplate::new(
+            dir.join("page.html"),
+            dir.join("index.html"),
+            HashMap::new(),
+        );
+        let error = template.generate().unwrap_err();
+        assert!(error.to_string().contains("page.html"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_render_template_unresolved() {
+        let dir = scratch_dir("template-unresolved");
+        std::fs::write(
+            dir.join("page.html"),
+            "{{ title }} {{ author }} {{ date }} {{ author }}",
+        )
+        .unwrap();
+
+        // each missing variable is named once and nothing is written
+        let variables = variables(&[("title", "Hello")]);
+        let mut template =
+            RenderTemplate::new(dir.join("page.html"), dir.join("index.html"), variables);
+        let error = template.generate().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "unresolved placeholders in {:?}: author, date",
+                dir.join("page.html")
+            )
+        );
+        assert!(!dir.join("index.html").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_render_template_integrity() {
+        let dir = scratch_dir("template-integrity");
+        let template = "<script src=\"app.js\" integrity=\"{{ app }}\"></script>";
+        std::fs::write(dir.join("page.html"), template).unwrap();
+
+        // the asset is generated, then hashed, then rendered
+        let mut builder = Builder::new();
+        builder
+            .require(StringFile::new(dir.join("app.js"), "run()"))
+            .unwrap();
+        let integrity = Rc::new(RefCell::new(Integrity::new(dir.join("app.js"))));
+        let render = RenderTemplate::new(
+            dir.join("page.html"),
+            dir.join("index.html"),
+            variables(&[]),
+        )
+        .variable("app", integrity);
+        builder.require(render).unwrap();
+        builder.generate().unwrap();
+
+        let html = std::fs::read_to_string(dir.join("index.html")).unwrap();
+        let value = sri(dir.join("app.js"), SriAlgorithm::Sha384).unwrap();
+        assert_eq!(
+            html,
+            format!("<script src=\"app.js\" integrity=\"{}\"></script>", value)
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_render_template_variable_equality() {
+        let integrity = |source: &str| Rc::new(RefCell::new(Integrity::new(source)));
+        let render = |source: &str| {
+            RenderTemplate::new("page.html", "index.html", variables(&[]))
+                .variable("app", integrity(source))
+        };
+
+        // templates filled from different resources are different
+        assert!(render("app.js") == render("app.js"));
+        assert!(render("app.js") != render("other.js"));
+    }
+}
@@ -0,0 +1,262 @@
+use regex::{Captures, Regex};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::{
+    builder::{Build, Builder, Node, Registration},
+    resource::Directory,
+};
+
+/// Writes a copy of an HTML page with a table of contents.
+///
+/// `<h2>` to `<h4>` headings without an `id` are given one slugged from
+/// their text, and a nested `<ul>` of links to the headings replaces any
+/// `<!-- toc -->` placeholder. The list is available from `toc` once the
+/// resource has been generated.
+#[derive(Debug)]
+pub struct TableOfContents {
+    id: Option<u64>,
+    source: PathBuf,
+    path: PathBuf,
+    toc: Option<String>,
+}
+
+impl TableOfContents {
+    pub fn new<P: AsRef<Path>>(source: P, path: P) -> Self {
+        Self {
+            id: None,
+            source: source.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
+            toc: None,
+        }
+    }
+
+    /// Returns the table of contents once the resource has been generated.
+    pub fn toc(&self) -> Option<&str> {
+        self.toc.as_deref()
+    }
+}
+
+impl PartialEq for TableOfContents {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Build for TableOfContents {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn equals(&self, other: Rc<RefCell<dyn Build>>) -> bool {
+        let other = other.borrow();
+        let any = other.as_any();
+        match any.downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => false,
+        }
+    }
+    fn id(&self) -> Option<u64> {
+        self.id
+    }
+    fn register(&mut self, id: u64) -> Result<Registration, Box<dyn std::error::Error>> {
+        self.id = Some(id);
+        Ok(Registration::Concrete(self.path.clone()))
+    }
+    fn dependencies(
+        &mut self,
+        builder: &mut Builder,
+    ) -> Result<Vec<Node>, Box<dyn std::error::Error>> {
+        let mut dependencies = match self.path.parent() {
+            Some(parent) => {
+                let directory =
+                    builder.require_ref(Rc::new(RefCell::new(Directory::new(parent))))?;
+                builder.label(&directory, "directory");
+                vec![directory]
+            }
+            None => vec![],
+        };
+        if let Some(node) = builder.producer(&self.source) {
+            builder.label(&node, "source");
+            dependencies.push(node);
+        }
+        Ok(dependencies)
+    }
+    fn generate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let html = std::fs::read_to_string(&self.source)
+            .map_err(|e| format!("failed to read {:?}: {}", self.source, e))?;
+
+        let heading = Regex::new(r"(?i)<h([2-4])\b([^>]*)>([\s\S]*?)(</h[2-4]\s*>)").unwrap();
+        let id = Regex::new(r#"(?i)\bid\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+        let tag = Regex::new(r"<[^>]*>").unwrap();
+
+        // existing ids are reserved so that generated ids do not collide
+        let mut ids: HashSet<String> = heading
+            .captures_iter(&html)
+            .filter_map(|heading| id.captures(heading.get(2)?.as_str()))
+            .map(|id| id.get(1).or(id.get(2)).unwrap().as_str().to_string())
+            .collect();
+        let mut headings: Vec<(usize, String, String)> = vec![];
+        let html = heading.replace_all(&html, |heading: &Captures| {
+            let level: usize = heading[1].parse().unwrap();
+            let text = tag.replace_all(&heading[3], "").trim().to_string();
+            if let Some(id) = id.captures(&heading[2]) {
+                let id = id.get(1).or(id.get(2)).unwrap().as_str().to_string();
+                headings.push((level, id, text));
+                return heading[0].to_string();
+            }
+            let slug = slug(&text);
+            let mut unique = slug.clone();
+            let mut suffix = 1;
+            while !ids.insert(unique.clone()) {
+                unique = format!("{}-{}", slug, suffix);
+                suffix += 1;
+            }
+            let open = format!("<h{}{} id=\"{}\">", level, &heading[2], unique);
+            headings.push((level, unique, text));
+            format!("{}{}{}", open, &heading[3], &heading[4])
+        });
+
+        let toc = list(&headings);
+        let html = html.replace("<!-- toc -->", &toc);
+        std::fs::write(&self.path, html)?;
+        self.toc = Some(toc);
+        Ok(())
+    }
+}
+
+// makes a lowercase, hyphenated anchor from heading text, without entities
+fn slug(text: &str) -> String {
+    let entity = Regex::new(r"&#?\w+;").unwrap();
+    let mut slug = String::new();
+    for c in entity.replace_all(text, " ").chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    match slug.is_empty() {
+        true => String::from("section"),
+        false => slug.to_string(),
+    }
+}
+
+// nests headings into lists, relative to the shallowest heading level
+fn list(headings: &[(usize, String, String)]) -> String {
+    let top = match headings.iter().map(|(level, _, _)| *level).min() {
+        Some(top) => top,
+        None => return String::new(),
+    };
+    let mut toc = String::new();
+    let mut depth = 0;
+    for (level, id, text) in headings {
+        let target = level - top + 1;
+        if target > depth {
+            while depth < target {
+                toc.push_str("<ul><li>");
+                depth += 1;
+            }
+        } else {
+            while depth > target {
+                toc.push_str("</li></ul>");
+                depth -= 1;
+            }
+            toc.push_str("</li><li>");
+        }
+        toc.push_str(&format!("<a href=\"#{}\">{}</a>", id, text));
+    }
+    while depth > 0 {
+        toc.push_str("</li></ul>");
+        depth -= 1;
+    }
+    toc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::scratch_dir;
+
+    fn heading(level: usize, id: &str) -> (usize, String, String) {
+        (level, id.to_string(), id.to_string())
+    }
+
+    #[test]
+    fn test_slug() {
+        assert_eq!(slug("Getting Started"), "getting-started");
+        assert_eq!(slug("  What's new in 2.0?  "), "what-s-new-in-2-0");
+        assert_eq!(slug("Tom &amp; Jerry"), "tom-jerry");
+        assert_eq!(slug("Ünïcode"), "ünïcode");
+        assert_eq!(slug("!!!"), "section");
+    }
+
+    #[test]
+    fn test_list() {
+        assert_eq!(list(&[]), "");
+
+        // deeper headings nest under the previous heading
+        let headings = [
+            heading(2, "a"),
+            heading(3, "b"),
+            heading(3, "c"),
+            heading(2, "d"),
+        ];
+        assert_eq!(
+            list(&headings),
+            concat!(
+                "<ul><li><a href=\"#a\">a</a>",
+                "<ul><li><a href=\"#b\">b</a></li><li><a href=\"#c\">c</a></li></ul>",
+                "</li><li><a href=\"#d\">d</a></li></ul>"
+            )
+        );
+
+        // levels are relative to the shallowest heading, and skipped levels
+        // open a list for each
+        let headings = [heading(3, "a"), heading(4, "b"), heading(3, "c")];
+        assert_eq!(
+            list(&headings),
+            concat!(
+                "<ul><li><a href=\"#a\">a</a>",
+                "<ul><li><a href=\"#b\">b</a></li></ul>",
+                "</li><li><a href=\"#c\">c</a></li></ul>"
+            )
+        );
+        let headings = [heading(2, "a"), heading(4, "b")];
+        assert_eq!(
+            list(&headings),
+            "<ul><li><a href=\"#a\">a</a><ul><li><ul><li><a href=\"#b\">b</a></li></ul></li></ul></li></ul>"
+        );
+    }
+
+    #[test]
+    fn test_table_of_contents_generate() {
+        let dir = scratch_dir("toc");
+        let html = concat!(
+            "<!-- toc -->",
+            "<h2>Intro</h2>",
+            "<h3 class=\"x\">Setup <em>fast</em></h3>",
+            "<h2 id=\"intro\">Kept</h2>",
+            "<h2>Intro</h2>",
+        );
+        std::fs::write(dir.join("source.html"), html).unwrap();
+
+        let mut toc = TableOfContents::new(dir.join("source.html"), dir.join("page.html"));
+        toc.generate().unwrap();
+
+        // existing ids are kept and generated ids avoid them
+        let page = std::fs::read_to_string(dir.join("page.html")).unwrap();
+        assert!(page.contains("<h2 id=\"intro-1\">Intro</h2>"));
+        assert!(page.contains("<h3 class=\"x\" id=\"setup-fast\">Setup <em>fast</em></h3>"));
+        assert!(page.contains("<h2 id=\"intro\">Kept</h2>"));
+        assert!(page.contains("<h2 id=\"intro-2\">Intro</h2>"));
+
+        let toc = toc.toc().unwrap();
+        assert!(page.starts_with(toc));
+        assert!(toc.contains("<a href=\"#setup-fast\">Setup fast</a>"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}